@@ -41,8 +41,22 @@ async fn main() -> Result<()> {
     let my_program = ipiis.sign_owned(*ipiis.account_ref(), my_program)?;
     let my_program = ipiis.sign_as_guarantor(my_program)?;
 
+    // grant the capabilities to the account
+    // note: nothing is granted by default, unless `ipwis_kernel_capabilities` is given
+    let capabilities = vec![
+        "ipwis_modules_ipiis".to_string(),
+        "ipwis_modules_stream".to_string(),
+    ];
+    ipwis
+        .kernel()
+        .policy
+        .lock()
+        .await
+        .grant(*ipiis.account_ref(), capabilities.iter().cloned().collect());
+
     // create a task and sign
     let mut task = Task::new_sandbox();
+    task.constraints.capabilities = capabilities;
     task.program = Some(my_program);
     let task = ipiis.sign_owned(*ipiis.account_ref(), task)?;
 
@@ -1,24 +1,34 @@
+use std::{collections::HashSet, sync::Arc, time::Duration};
+
 use ipiis_api::common::Ipiis;
 use ipis::{
     async_trait::async_trait,
     core::{
-        account::{GuaranteeSigned, GuarantorSigned},
+        account::{AccountRef, GuaranteeSigned, GuarantorSigned},
         anyhow::{bail, Result},
         data::Data,
+        value::hash::Hash,
     },
     env::Infer,
     futures::TryFutureExt,
-    object::data::ObjectData,
+    log::warn,
+    path::Path,
+    tokio::sync::Mutex,
 };
 use ipsis_common::Ipsis;
-use ipwis_common::{Ipwis, ResourceId, Task, TaskPoll};
-use ipwis_kernel::Kernel;
+use ipwis_common::{
+    Ipwis, ProgramArtifact, ResourceId, Task, TaskInfo, TaskLogs, TaskPipeline, TaskPoll,
+};
+use ipwis_kernel::{program_cache, task_graph::TaskPrograms, Kernel};
 
 pub type IpwisClient = IpwisClientInner<::ipiis_api::client::IpiisClient>;
 
 pub struct IpwisClientInner<IpiisClient> {
     pub ipiis: IpiisClient,
     kernel: Kernel,
+    // note: the native code is loaded only if compiled by this node
+    // note: the artifacts are not trusted after restart, and compiled from the programs instead
+    artifacts: Mutex<HashSet<Hash>>,
 }
 
 impl<IpiisClient> AsRef<::ipiis_api::client::IpiisClient> for IpwisClientInner<IpiisClient>
@@ -69,8 +79,93 @@ impl<IpiisClient> IpwisClientInner<IpiisClient> {
         Ok(Self {
             ipiis,
             kernel: Kernel::try_new().await?,
+            artifacts: Default::default(),
         })
     }
+
+    // note: the operators can configure the kernel, e.g. granting the capabilities
+    pub fn kernel(&self) -> &Kernel {
+        &self.kernel
+    }
+
+    async fn fetch_program(&self, program: &Data<GuarantorSigned, Path>) -> Result<Arc<Vec<u8>>>
+    where
+        IpiisClient: Ipsis + Send + Sync,
+    {
+        // note: the programs are content-addressed, so they can be reused
+        match self.kernel.programs.get(&program.data).await {
+            Some(program) => Ok(program),
+            None => {
+                let bytes = self.ipiis.get(program).await?;
+                self.kernel.programs.put(&program.data, bytes).await
+            }
+        }
+    }
+
+    async fn fetch_artifact(&self, task: &Task) -> Result<Option<ProgramArtifact>>
+    where
+        IpiisClient: Ipsis + Send + Sync,
+    {
+        let (artifact, program) = match (&task.artifact, &task.program) {
+            (Some(artifact), Some(program)) => (artifact, program),
+            _ => return Ok(None),
+        };
+
+        // accept the artifacts compiled by this node only
+        if !self.artifacts.lock().await.contains(&artifact.data.value) {
+            warn!("untrusted artifact: {}", artifact.data.value);
+            return Ok(None);
+        }
+
+        // note: the IPSIS fetches are not verified, but the artifact is loaded as a native code
+        let fetched: ProgramArtifact = self.ipiis.get(artifact).await?;
+        program_cache::verify(&artifact.data, &fetched)?;
+        if fetched.source != program.data {
+            bail!("the artifact does not match the program");
+        }
+        Ok(Some(fetched))
+    }
+
+    async fn fetch_programs(&self, task: &Task) -> Result<TaskPrograms>
+    where
+        IpiisClient: Ipsis + Send + Sync,
+    {
+        fn collect<'a>(task: &'a Task, programs: &mut Vec<&'a Data<GuarantorSigned, Path>>) {
+            programs.extend(&task.program);
+            for child in task.children.values().chain(&task.exceptions) {
+                collect(child, programs);
+            }
+        }
+
+        let mut programs = vec![];
+        collect(task, &mut programs);
+
+        let mut fetched = TaskPrograms::default();
+        for program in programs {
+            if !fetched.contains_key(&program.data.value) {
+                fetched.insert(program.data.value, self.fetch_program(program).await?);
+            }
+        }
+        Ok(fetched)
+    }
+
+    async fn fetch_pipeline_programs(&self, pipeline: &TaskPipeline) -> Result<TaskPrograms>
+    where
+        IpiisClient: Ipsis + Send + Sync,
+    {
+        let mut fetched = TaskPrograms::default();
+        for stage in &pipeline.stages {
+            let program = &stage.program;
+            if !fetched.contains_key(&program.data.value) {
+                fetched.insert(program.data.value, self.fetch_program(program).await?);
+            }
+        }
+        Ok(fetched)
+    }
+
+    pub(crate) async fn task_list_by(&self, account: &AccountRef) -> Result<Vec<TaskInfo>> {
+        self.kernel.list(account).await
+    }
 }
 
 #[async_trait]
@@ -89,14 +184,43 @@ where
         let task = self.ipiis.sign_as_guarantor(task)?;
         let guarantee = task.metadata.guarantee.account;
 
-        match &task.program {
-            Some(program) => {
-                let program: Vec<u8> = self.ipiis.get(program).await?;
-                let id = self.kernel.spawn_raw(task, &program).await?;
-                self.ipiis.sign_owned(guarantee, id)
-            }
+        let program = match &task.program {
+            Some(program) => program,
             None => bail!("Empty program"),
-        }
+        };
+
+        let id = if !task.children.is_empty() || !task.exceptions.is_empty() {
+            // note: the task graphs are compiled from the programs
+            if task.artifact.is_some() {
+                bail!("the task graphs cannot be precompiled");
+            }
+            let programs = self.fetch_programs(&task).await?;
+            self.kernel.spawn_graph(task, programs).await?
+        } else if let Some(artifact) = self.fetch_artifact(&task).await? {
+            // safety: the artifact is compiled by this node, and verified
+            unsafe {
+                self.kernel
+                    .spawn_precompiled(task, &artifact.source, &artifact.bytes)
+                    .await?
+            }
+        } else {
+            let program = self.fetch_program(program).await?;
+            self.kernel.spawn_raw(task, &program).await?
+        };
+        self.ipiis.sign_owned(guarantee, id)
+    }
+
+    async fn task_spawn_pipeline(
+        &self,
+        pipeline: Data<GuaranteeSigned, TaskPipeline>,
+    ) -> Result<Data<GuaranteeSigned, ResourceId>> {
+        let pipeline = self.ipiis.sign_as_guarantor(pipeline)?;
+        let guarantee = pipeline.metadata.guarantee.account;
+
+        // note: the intermediate outputs are kept in the kernel
+        let programs = self.fetch_pipeline_programs(&pipeline).await?;
+        let id = self.kernel.spawn_pipeline(pipeline, programs).await?;
+        self.ipiis.sign_owned(guarantee, id)
     }
 
     async fn task_poll(
@@ -106,12 +230,60 @@ where
         let guarantee = id.metadata.guarantee.account;
 
         self.kernel
-            .poll(&id.data)
+            .poll(&id.data, &id.metadata.guarantor)
+            .await
+            .and_then(|poll| self.ipiis.sign_owned(guarantee, poll))
+    }
+
+    async fn task_wait_timeout(
+        &self,
+        id: Data<GuarantorSigned, ResourceId>,
+        timeout: Option<Duration>,
+    ) -> Result<Data<GuaranteeSigned, TaskPoll>> {
+        let guarantee = id.metadata.guarantee.account;
+
+        self.kernel
+            .wait_timeout(&id.data, timeout, &id.metadata.guarantor)
             .await
             .and_then(|poll| self.ipiis.sign_owned(guarantee, poll))
     }
 
-    async fn task_wait(&self, id: Data<GuarantorSigned, ResourceId>) -> Result<Box<ObjectData>> {
-        self.kernel.wait(&id.data).await
+    async fn task_precompile(
+        &self,
+        program: Data<GuarantorSigned, Path>,
+    ) -> Result<Data<GuaranteeSigned, Path>> {
+        let guarantor = program.metadata.guarantor;
+
+        // compile the program
+        let bytes = self.fetch_program(&program).await?;
+        let artifact = ProgramArtifact {
+            source: program.data,
+            bytes: self.kernel.precompile(&bytes).await?,
+        };
+
+        // publish the artifact
+        let artifact = self.ipiis.put(&artifact).await?;
+        self.artifacts.lock().await.insert(artifact.value);
+        self.ipiis.sign_owned(guarantor, artifact)
+    }
+
+    async fn task_kill(&self, id: Data<GuarantorSigned, ResourceId>) -> Result<()> {
+        self.kernel.kill(&id.data, &id.metadata.guarantor).await
+    }
+
+    async fn task_release(&self, id: Data<GuarantorSigned, ResourceId>) -> Result<()> {
+        self.kernel.release(&id.data, &id.metadata.guarantor).await
+    }
+
+    async fn task_list(&self) -> Result<Vec<TaskInfo>> {
+        self.task_list_by(self.ipiis.account_ref()).await
+    }
+
+    async fn task_inspect(&self, id: Data<GuarantorSigned, ResourceId>) -> Result<TaskInfo> {
+        self.kernel.inspect(&id.data, &id.metadata.guarantor).await
+    }
+
+    async fn task_logs(&self, id: Data<GuarantorSigned, ResourceId>) -> Result<TaskLogs> {
+        self.kernel.logs(&id.data, &id.metadata.guarantor).await
     }
 }
@@ -1,12 +1,20 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 use ipiis_api::{
     client::IpiisClient,
     common::{handle_external_call, Ipiis, ServerResult},
     server::IpiisServer,
 };
-use ipis::{async_trait::async_trait, core::anyhow::Result, env::Infer};
-use ipwis_common::Ipwis;
+use ipis::{
+    async_trait::async_trait,
+    core::{
+        account::{AccountRef, GuarantorSigned},
+        anyhow::{bail, Result},
+        data::Data,
+    },
+    env::Infer,
+};
+use ipwis_common::{Ipwis, ResourceId};
 
 use crate::client::IpwisClientInner;
 
@@ -48,7 +56,15 @@ handle_external_call!(
     request: ::ipwis_common::io => {
         Protocol => handle_protocol,
         Spawn => handle_spawn,
+        SpawnPipeline => handle_spawn_pipeline,
         Poll => handle_poll,
+        Wait => handle_wait,
+        Precompile => handle_precompile,
+        Kill => handle_kill,
+        Release => handle_release,
+        List => handle_list,
+        Inspect => handle_inspect,
+        Logs => handle_logs,
     },
 );
 
@@ -100,6 +116,31 @@ impl IpwisServer {
         })
     }
 
+    async fn handle_spawn_pipeline(
+        client: &IpwisClientInner<IpiisServer>,
+        req: ::ipwis_common::io::request::SpawnPipeline<'static>,
+    ) -> Result<::ipwis_common::io::response::SpawnPipeline<'static>> {
+        // unpack sign
+        let sign_as_guarantee = req.__sign.into_owned().await?;
+
+        // unpack data
+        let ctx = sign_as_guarantee.clone();
+
+        // handle data
+        let id = client.task_spawn_pipeline(ctx).await?;
+
+        // sign data
+        let server: &IpiisServer = client.as_ref();
+        let sign = server.sign_as_guarantor(sign_as_guarantee)?;
+
+        // pack data
+        Ok(::ipwis_common::io::response::SpawnPipeline {
+            __lifetime: Default::default(),
+            __sign: ::ipis::stream::DynStream::Owned(sign),
+            id: ::ipis::stream::DynStream::Owned(id),
+        })
+    }
+
     async fn handle_poll(
         client: &IpwisClientInner<IpiisServer>,
         req: ::ipwis_common::io::request::Poll<'static>,
@@ -109,6 +150,7 @@ impl IpwisServer {
 
         // unpack data
         let id = req.id.into_owned().await?;
+        ensure_requester(&sign_as_guarantee.metadata.guarantee.account, &id)?;
 
         // handle data
         let poll = client.task_poll(id).await?;
@@ -124,4 +166,192 @@ impl IpwisServer {
             poll: ::ipis::stream::DynStream::Owned(poll),
         })
     }
+
+    async fn handle_wait(
+        client: &IpwisClientInner<IpiisServer>,
+        req: ::ipwis_common::io::request::Wait<'static>,
+    ) -> Result<::ipwis_common::io::response::Wait<'static>> {
+        // unpack sign
+        let sign_as_guarantee = req.__sign.into_owned().await?;
+
+        // unpack data
+        let id = req.id.into_owned().await?;
+        ensure_requester(&sign_as_guarantee.metadata.guarantee.account, &id)?;
+        let timeout = sign_as_guarantee.timeout_ms.map(Duration::from_millis);
+
+        // handle data
+        let poll = client.task_wait_timeout(id, timeout).await?;
+
+        // sign data
+        let server: &IpiisServer = client.as_ref();
+        let sign = server.sign_as_guarantor(sign_as_guarantee)?;
+
+        // pack data
+        Ok(::ipwis_common::io::response::Wait {
+            __lifetime: Default::default(),
+            __sign: ::ipis::stream::DynStream::Owned(sign),
+            poll: ::ipis::stream::DynStream::Owned(poll),
+        })
+    }
+
+    async fn handle_precompile(
+        client: &IpwisClientInner<IpiisServer>,
+        req: ::ipwis_common::io::request::Precompile<'static>,
+    ) -> Result<::ipwis_common::io::response::Precompile<'static>> {
+        // unpack sign
+        let sign_as_guarantee = req.__sign.into_owned().await?;
+
+        // unpack data
+        let program = req.program.into_owned().await?;
+
+        // handle data
+        let artifact = client.task_precompile(program).await?;
+
+        // sign data
+        let server: &IpiisServer = client.as_ref();
+        let sign = server.sign_as_guarantor(sign_as_guarantee)?;
+
+        // pack data
+        Ok(::ipwis_common::io::response::Precompile {
+            __lifetime: Default::default(),
+            __sign: ::ipis::stream::DynStream::Owned(sign),
+            artifact: ::ipis::stream::DynStream::Owned(artifact),
+        })
+    }
+
+    async fn handle_kill(
+        client: &IpwisClientInner<IpiisServer>,
+        req: ::ipwis_common::io::request::Kill<'static>,
+    ) -> Result<::ipwis_common::io::response::Kill<'static>> {
+        // unpack sign
+        let sign_as_guarantee = req.__sign.into_owned().await?;
+
+        // unpack data
+        let id = req.id.into_owned().await?;
+        ensure_requester(&sign_as_guarantee.metadata.guarantee.account, &id)?;
+
+        // handle data
+        client.task_kill(id).await?;
+
+        // sign data
+        let server: &IpiisServer = client.as_ref();
+        let sign = server.sign_as_guarantor(sign_as_guarantee)?;
+
+        // pack data
+        Ok(::ipwis_common::io::response::Kill {
+            __lifetime: Default::default(),
+            __sign: ::ipis::stream::DynStream::Owned(sign),
+        })
+    }
+
+    async fn handle_release(
+        client: &IpwisClientInner<IpiisServer>,
+        req: ::ipwis_common::io::request::Release<'static>,
+    ) -> Result<::ipwis_common::io::response::Release<'static>> {
+        // unpack sign
+        let sign_as_guarantee = req.__sign.into_owned().await?;
+
+        // unpack data
+        let id = req.id.into_owned().await?;
+        ensure_requester(&sign_as_guarantee.metadata.guarantee.account, &id)?;
+
+        // handle data
+        client.task_release(id).await?;
+
+        // sign data
+        let server: &IpiisServer = client.as_ref();
+        let sign = server.sign_as_guarantor(sign_as_guarantee)?;
+
+        // pack data
+        Ok(::ipwis_common::io::response::Release {
+            __lifetime: Default::default(),
+            __sign: ::ipis::stream::DynStream::Owned(sign),
+        })
+    }
+
+    async fn handle_list(
+        client: &IpwisClientInner<IpiisServer>,
+        req: ::ipwis_common::io::request::List<'static>,
+    ) -> Result<::ipwis_common::io::response::List<'static>> {
+        // unpack sign
+        let sign_as_guarantee = req.__sign.into_owned().await?;
+
+        // handle data
+        // note: an account can only see its own tasks
+        let tasks = client
+            .task_list_by(&sign_as_guarantee.metadata.guarantee.account)
+            .await?;
+
+        // sign data
+        let server: &IpiisServer = client.as_ref();
+        let sign = server.sign_as_guarantor(sign_as_guarantee)?;
+
+        // pack data
+        Ok(::ipwis_common::io::response::List {
+            __lifetime: Default::default(),
+            __sign: ::ipis::stream::DynStream::Owned(sign),
+            tasks: ::ipis::stream::DynStream::Owned(tasks),
+        })
+    }
+
+    async fn handle_inspect(
+        client: &IpwisClientInner<IpiisServer>,
+        req: ::ipwis_common::io::request::Inspect<'static>,
+    ) -> Result<::ipwis_common::io::response::Inspect<'static>> {
+        // unpack sign
+        let sign_as_guarantee = req.__sign.into_owned().await?;
+
+        // unpack data
+        let id = req.id.into_owned().await?;
+        ensure_requester(&sign_as_guarantee.metadata.guarantee.account, &id)?;
+
+        // handle data
+        let info = client.task_inspect(id).await?;
+
+        // sign data
+        let server: &IpiisServer = client.as_ref();
+        let sign = server.sign_as_guarantor(sign_as_guarantee)?;
+
+        // pack data
+        Ok(::ipwis_common::io::response::Inspect {
+            __lifetime: Default::default(),
+            __sign: ::ipis::stream::DynStream::Owned(sign),
+            info: ::ipis::stream::DynStream::Owned(info),
+        })
+    }
+
+    async fn handle_logs(
+        client: &IpwisClientInner<IpiisServer>,
+        req: ::ipwis_common::io::request::Logs<'static>,
+    ) -> Result<::ipwis_common::io::response::Logs<'static>> {
+        // unpack sign
+        let sign_as_guarantee = req.__sign.into_owned().await?;
+
+        // unpack data
+        let id = req.id.into_owned().await?;
+        ensure_requester(&sign_as_guarantee.metadata.guarantee.account, &id)?;
+
+        // handle data
+        let logs = client.task_logs(id).await?;
+
+        // sign data
+        let server: &IpiisServer = client.as_ref();
+        let sign = server.sign_as_guarantor(sign_as_guarantee)?;
+
+        // pack data
+        Ok(::ipwis_common::io::response::Logs {
+            __lifetime: Default::default(),
+            __sign: ::ipis::stream::DynStream::Owned(sign),
+            logs: ::ipis::stream::DynStream::Owned(logs),
+        })
+    }
+}
+
+fn ensure_requester(requester: &AccountRef, id: &Data<GuarantorSigned, ResourceId>) -> Result<()> {
+    // note: the task id should be signed by the requester
+    if requester == &id.metadata.guarantor {
+        Ok(())
+    } else {
+        bail!("the requester is not the signer of the task id")
+    }
 }
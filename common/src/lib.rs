@@ -1,18 +1,29 @@
+use std::time::Duration;
+
 use ipiis_common::{define_io, external_call, Ipiis, ServerResult};
 use ipis::{
     async_trait::async_trait,
     core::{
         account::{GuaranteeSigned, GuarantorSigned},
-        anyhow::{bail, Result},
+        anyhow::Result,
         data::Data,
     },
     object::data::ObjectData,
-    tokio,
+    path::Path,
 };
 
 pub use ipwis_modules_core_common::resource_store::ResourceId;
-pub use ipwis_modules_task_common::{task::Task, task_poll::TaskPoll};
-pub use ipwis_modules_task_common_wasi::program::Program;
+pub use ipwis_modules_task_common::{
+    task::Task,
+    task_error::TaskError,
+    task_info::TaskInfo,
+    task_logs::TaskLogs,
+    task_pipeline::{TaskPipeline, TaskPipelineStage},
+    task_poll::TaskPoll,
+    task_status::TaskStatus,
+    task_wait::TaskWait,
+};
+pub use ipwis_modules_task_common_wasi::{program::Program, program_artifact::ProgramArtifact};
 
 #[async_trait]
 pub trait Ipwis {
@@ -23,20 +34,46 @@ pub trait Ipwis {
         task: Data<GuaranteeSigned, Task>,
     ) -> Result<Data<GuaranteeSigned, ResourceId>>;
 
+    async fn task_spawn_pipeline(
+        &self,
+        pipeline: Data<GuaranteeSigned, TaskPipeline>,
+    ) -> Result<Data<GuaranteeSigned, ResourceId>>;
+
     async fn task_poll(
         &self,
         id: Data<GuarantorSigned, ResourceId>,
     ) -> Result<Data<GuaranteeSigned, TaskPoll>>;
 
+    async fn task_wait_timeout(
+        &self,
+        id: Data<GuarantorSigned, ResourceId>,
+        timeout: Option<Duration>,
+    ) -> Result<Data<GuaranteeSigned, TaskPoll>>;
+
     async fn task_wait(&self, id: Data<GuarantorSigned, ResourceId>) -> Result<Box<ObjectData>> {
         loop {
-            match self.task_poll(id).await?.data {
-                TaskPoll::Pending => tokio::task::yield_now().await,
+            match self.task_wait_timeout(id, None).await?.data {
+                TaskPoll::Pending { .. } => continue,
                 TaskPoll::Ready(outputs) => break Ok(outputs),
-                TaskPoll::Trap(errors) => bail!("{}", errors.msg),
+                TaskPoll::Failed(error) => break Err(error.into()),
             }
         }
     }
+
+    async fn task_precompile(
+        &self,
+        program: Data<GuarantorSigned, Path>,
+    ) -> Result<Data<GuaranteeSigned, Path>>;
+
+    async fn task_kill(&self, id: Data<GuarantorSigned, ResourceId>) -> Result<()>;
+
+    async fn task_release(&self, id: Data<GuarantorSigned, ResourceId>) -> Result<()>;
+
+    async fn task_list(&self) -> Result<Vec<TaskInfo>>;
+
+    async fn task_inspect(&self, id: Data<GuarantorSigned, ResourceId>) -> Result<TaskInfo>;
+
+    async fn task_logs(&self, id: Data<GuarantorSigned, ResourceId>) -> Result<TaskLogs>;
 }
 
 #[async_trait]
@@ -83,6 +120,27 @@ where
         Ok(id)
     }
 
+    async fn task_spawn_pipeline(
+        &self,
+        pipeline: Data<GuaranteeSigned, TaskPipeline>,
+    ) -> Result<Data<GuaranteeSigned, ResourceId>> {
+        // next target
+        let target = pipeline.metadata.guarantor;
+
+        // external call
+        let (id,) = external_call!(
+            client: self,
+            target: KIND.as_ref() => &target,
+            request: crate::io => SpawnPipeline,
+            sign: pipeline,
+            inputs: { },
+            outputs: { id, },
+        );
+
+        // unpack response
+        Ok(id)
+    }
+
     async fn task_poll(
         &self,
         id: Data<GuarantorSigned, ResourceId>,
@@ -105,6 +163,158 @@ where
         // unpack response
         Ok(poll)
     }
+
+    async fn task_wait_timeout(
+        &self,
+        id: Data<GuarantorSigned, ResourceId>,
+        timeout: Option<Duration>,
+    ) -> Result<Data<GuaranteeSigned, TaskPoll>> {
+        // next target
+        let target = self.get_account_primary(KIND.as_ref()).await?;
+
+        // pack request
+        let wait = TaskWait {
+            timeout_ms: timeout
+                .map(|timeout| timeout.as_millis().try_into())
+                .transpose()?,
+        };
+
+        // external call
+        let (poll,) = external_call!(
+            client: self,
+            target: KIND.as_ref() => &target,
+            request: crate::io => Wait,
+            sign: self.sign_owned(target, wait)?,
+            inputs: {
+                id: id,
+            },
+            outputs: { poll, },
+        );
+
+        // unpack response
+        Ok(poll)
+    }
+
+    async fn task_precompile(
+        &self,
+        program: Data<GuarantorSigned, Path>,
+    ) -> Result<Data<GuaranteeSigned, Path>> {
+        // next target
+        let target = self.get_account_primary(KIND.as_ref()).await?;
+
+        // external call
+        let (artifact,) = external_call!(
+            client: self,
+            target: KIND.as_ref() => &target,
+            request: crate::io => Precompile,
+            sign: self.sign_owned(target, ())?,
+            inputs: {
+                program: program,
+            },
+            outputs: { artifact, },
+        );
+
+        // unpack response
+        Ok(artifact)
+    }
+
+    async fn task_kill(&self, id: Data<GuarantorSigned, ResourceId>) -> Result<()> {
+        // next target
+        let target = self.get_account_primary(KIND.as_ref()).await?;
+
+        // external call
+        external_call!(
+            client: self,
+            target: KIND.as_ref() => &target,
+            request: crate::io => Kill,
+            sign: self.sign_owned(target, ())?,
+            inputs: {
+                id: id,
+            },
+            outputs: { },
+        );
+
+        // unpack response
+        Ok(())
+    }
+
+    async fn task_release(&self, id: Data<GuarantorSigned, ResourceId>) -> Result<()> {
+        // next target
+        let target = self.get_account_primary(KIND.as_ref()).await?;
+
+        // external call
+        external_call!(
+            client: self,
+            target: KIND.as_ref() => &target,
+            request: crate::io => Release,
+            sign: self.sign_owned(target, ())?,
+            inputs: {
+                id: id,
+            },
+            outputs: { },
+        );
+
+        // unpack response
+        Ok(())
+    }
+
+    async fn task_list(&self) -> Result<Vec<TaskInfo>> {
+        // next target
+        let target = self.get_account_primary(KIND.as_ref()).await?;
+
+        // external call
+        let (tasks,) = external_call!(
+            client: self,
+            target: KIND.as_ref() => &target,
+            request: crate::io => List,
+            sign: self.sign_owned(target, ())?,
+            inputs: { },
+            outputs: { tasks, },
+        );
+
+        // unpack response
+        Ok(tasks)
+    }
+
+    async fn task_inspect(&self, id: Data<GuarantorSigned, ResourceId>) -> Result<TaskInfo> {
+        // next target
+        let target = self.get_account_primary(KIND.as_ref()).await?;
+
+        // external call
+        let (info,) = external_call!(
+            client: self,
+            target: KIND.as_ref() => &target,
+            request: crate::io => Inspect,
+            sign: self.sign_owned(target, ())?,
+            inputs: {
+                id: id,
+            },
+            outputs: { info, },
+        );
+
+        // unpack response
+        Ok(info)
+    }
+
+    async fn task_logs(&self, id: Data<GuarantorSigned, ResourceId>) -> Result<TaskLogs> {
+        // next target
+        let target = self.get_account_primary(KIND.as_ref()).await?;
+
+        // external call
+        let (logs,) = external_call!(
+            client: self,
+            target: KIND.as_ref() => &target,
+            request: crate::io => Logs,
+            sign: self.sign_owned(target, ())?,
+            inputs: {
+                id: id,
+            },
+            outputs: { logs, },
+        );
+
+        // unpack response
+        Ok(logs)
+    }
 }
 
 define_io! {
@@ -126,6 +336,15 @@ define_io! {
         output_sign: Data<GuarantorSigned, Task>,
         generics: { },
     },
+    SpawnPipeline {
+        inputs: { },
+        input_sign: Data<GuaranteeSigned, TaskPipeline>,
+        outputs: {
+            id: Data<GuaranteeSigned, ResourceId>,
+        },
+        output_sign: Data<GuarantorSigned, TaskPipeline>,
+        generics: { },
+    },
     Poll {
         inputs: {
             id: Data<GuarantorSigned, ResourceId>,
@@ -137,6 +356,77 @@ define_io! {
         output_sign: Data<GuarantorSigned, ()>,
         generics: { },
     },
+    Wait {
+        inputs: {
+            id: Data<GuarantorSigned, ResourceId>,
+        },
+        input_sign: Data<GuaranteeSigned, TaskWait>,
+        outputs: {
+            poll: Data<GuaranteeSigned, TaskPoll>,
+        },
+        output_sign: Data<GuarantorSigned, TaskWait>,
+        generics: { },
+    },
+    Precompile {
+        inputs: {
+            program: Data<GuarantorSigned, Path>,
+        },
+        input_sign: Data<GuaranteeSigned, ()>,
+        outputs: {
+            artifact: Data<GuaranteeSigned, Path>,
+        },
+        output_sign: Data<GuarantorSigned, ()>,
+        generics: { },
+    },
+    Kill {
+        inputs: {
+            id: Data<GuarantorSigned, ResourceId>,
+        },
+        input_sign: Data<GuaranteeSigned, ()>,
+        outputs: { },
+        output_sign: Data<GuarantorSigned, ()>,
+        generics: { },
+    },
+    Release {
+        inputs: {
+            id: Data<GuarantorSigned, ResourceId>,
+        },
+        input_sign: Data<GuaranteeSigned, ()>,
+        outputs: { },
+        output_sign: Data<GuarantorSigned, ()>,
+        generics: { },
+    },
+    List {
+        inputs: { },
+        input_sign: Data<GuaranteeSigned, ()>,
+        outputs: {
+            tasks: Vec<TaskInfo>,
+        },
+        output_sign: Data<GuarantorSigned, ()>,
+        generics: { },
+    },
+    Inspect {
+        inputs: {
+            id: Data<GuarantorSigned, ResourceId>,
+        },
+        input_sign: Data<GuaranteeSigned, ()>,
+        outputs: {
+            info: TaskInfo,
+        },
+        output_sign: Data<GuarantorSigned, ()>,
+        generics: { },
+    },
+    Logs {
+        inputs: {
+            id: Data<GuarantorSigned, ResourceId>,
+        },
+        input_sign: Data<GuaranteeSigned, ()>,
+        outputs: {
+            logs: TaskLogs,
+        },
+        output_sign: Data<GuarantorSigned, ()>,
+        generics: { },
+    },
 }
 
 ::ipis::lazy_static::lazy_static! {
@@ -28,7 +28,11 @@ async fn main() -> Result<()> {
     const BINARY: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/output.wasm"));
 
     // create a task and sign
-    let task = Task::new_sandbox();
+    let mut task = Task::new_sandbox();
+    task.constraints.capabilities = vec![
+        "ipwis_modules_ipiis".to_string(),
+        "ipwis_modules_stream".to_string(),
+    ];
     let task = client.sign_owned(*client.account_ref(), task)?;
     let task = client.sign_as_guarantor(task)?;
 
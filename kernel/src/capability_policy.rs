@@ -0,0 +1,111 @@
+use std::collections::{BTreeSet, HashMap};
+
+use ipis::core::{
+    account::AccountRef,
+    anyhow::{bail, Result},
+};
+
+#[derive(Clone, Debug)]
+pub struct CapabilityPolicy {
+    // note: `None` grants all capabilities
+    default: Option<BTreeSet<String>>,
+    accounts: HashMap<AccountRef, BTreeSet<String>>,
+}
+
+impl Default for CapabilityPolicy {
+    fn default() -> Self {
+        // note: nothing is granted by default
+        Self::with_default(Some(Default::default()))
+    }
+}
+
+impl CapabilityPolicy {
+    pub fn with_default(capabilities: Option<BTreeSet<String>>) -> Self {
+        Self {
+            default: capabilities,
+            accounts: Default::default(),
+        }
+    }
+
+    pub fn grant(&mut self, account: AccountRef, capabilities: BTreeSet<String>) {
+        self.accounts.insert(account, capabilities);
+    }
+
+    pub fn revoke(&mut self, account: &AccountRef) {
+        self.accounts.remove(account);
+    }
+
+    pub fn check(&self, account: &AccountRef, requested: &[String]) -> Result<()> {
+        let granted = match self.accounts.get(account) {
+            Some(granted) => granted,
+            None => match &self.default {
+                Some(granted) => granted,
+                None => return Ok(()),
+            },
+        };
+
+        for capability in requested {
+            if !granted.contains(capability) {
+                bail!("capability not granted: {capability}");
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ipis::core::account::Account;
+
+    use super::*;
+
+    fn capabilities(capabilities: &[&str]) -> Vec<String> {
+        capabilities.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn test_default_grants_nothing() {
+        let account = *Account::generate().account_ref();
+        let policy = CapabilityPolicy::default();
+
+        assert!(policy.check(&account, &[]).is_ok());
+        assert!(policy.check(&account, &capabilities(&["a"])).is_err());
+    }
+
+    #[test]
+    fn test_permissive() {
+        let account = *Account::generate().account_ref();
+        let policy = CapabilityPolicy::with_default(None);
+
+        assert!(policy.check(&account, &capabilities(&["a", "b"])).is_ok());
+    }
+
+    #[test]
+    fn test_default_grant() {
+        let account = *Account::generate().account_ref();
+        let policy =
+            CapabilityPolicy::with_default(Some(capabilities(&["a"]).into_iter().collect()));
+
+        assert!(policy.check(&account, &capabilities(&["a"])).is_ok());
+        assert!(policy.check(&account, &capabilities(&["a", "b"])).is_err());
+    }
+
+    #[test]
+    fn test_account_grant_overrides_default() {
+        let alice = *Account::generate().account_ref();
+        let bob = *Account::generate().account_ref();
+        let mut policy =
+            CapabilityPolicy::with_default(Some(capabilities(&["a"]).into_iter().collect()));
+        policy.grant(alice, capabilities(&["b"]).into_iter().collect());
+
+        assert!(policy.check(&alice, &capabilities(&["b"])).is_ok());
+        assert!(policy.check(&alice, &capabilities(&["a"])).is_err());
+        assert!(policy.check(&bob, &capabilities(&["a"])).is_ok());
+        assert!(policy.check(&bob, &capabilities(&["b"])).is_err());
+
+        // fall back to the default
+        policy.revoke(&alice);
+        assert!(policy.check(&alice, &capabilities(&["a"])).is_ok());
+        assert!(policy.check(&alice, &capabilities(&["b"])).is_err());
+    }
+}
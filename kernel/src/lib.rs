@@ -1,20 +1,90 @@
-use std::sync::Arc;
+pub mod capability_policy;
+pub mod task_graph;
+
+pub use ipwis_modules_task_api_wasi::program_cache;
+mod task_pipeline;
+
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{Arc, Weak},
+    time::Duration,
+};
 
 use ipis::{
-    core::{account::GuarantorSigned, anyhow::Result, data::Data, value::text::Text},
+    async_trait::async_trait,
+    core::{
+        account::{AccountRef, GuarantorSigned},
+        anyhow::{bail, Result},
+        data::Data,
+    },
+    env::infer,
+    futures::{future::BoxFuture, FutureExt},
+    log::warn,
     object::data::ObjectData,
-    tokio::sync::Mutex,
+    path::Path,
+    tokio::{
+        self,
+        sync::{watch, Mutex},
+        time::Instant,
+    },
 };
 use ipwis_modules_core_common::resource_store::{ResourceId, ResourceStore};
-use ipwis_modules_task_api::{task_instance::TaskInstance, task_manager::TaskManager};
-use ipwis_modules_task_api_wasi::task_manager::IpwisTaskManager;
-use ipwis_modules_task_common::{task::Task, task_poll::TaskPoll};
+use ipwis_modules_subtask_api::{SubtaskModule, SubtaskRegistry};
+use ipwis_modules_task_api::{
+    task_instance::TaskInstance, task_manager::TaskManager, task_state::TaskState,
+};
+use ipwis_modules_task_api_wasi::{
+    module_cache::ModuleCache, program_cache::ProgramCache, task_manager::IpwisTaskManager,
+};
+use ipwis_modules_task_common::{
+    task::Task,
+    task_info::{TaskChildInfo, TaskInfo},
+    task_logs::{TaskChildLogs, TaskLogs},
+    task_pipeline::TaskPipeline,
+    task_poll::TaskPoll,
+    task_status::TaskStatus,
+};
+
+use crate::{capability_policy::CapabilityPolicy, task_graph::TaskPrograms};
 
 type IpwisTaskInstance = TaskInstance<Box<ObjectData>, IpwisTaskManager>;
+type IpwisTaskState = Arc<Mutex<TaskState<IpwisTaskManager>>>;
+
+/// The default retention window of the finished tasks' results.
+const DEFAULT_RESULT_TTL_MS: u64 = 10 * 60 * 1000;
 
 pub struct Kernel {
     manager: Arc<IpwisTaskManager>,
-    instances: Arc<Mutex<ResourceStore<IpwisTaskInstance>>>,
+    pub policy: Mutex<CapabilityPolicy>,
+    pub programs: Arc<ProgramCache>,
+    tasks: Arc<TaskTable>,
+}
+
+struct TaskTable {
+    this: Weak<Self>,
+    instances: Mutex<ResourceStore<IpwisTaskInstance>>,
+    owners: Mutex<HashMap<ResourceId, TaskOwner>>,
+    results: Mutex<HashMap<ResourceId, TaskResult>>,
+    result_ttl: Duration,
+}
+
+#[derive(Copy, Clone)]
+struct TaskOwner {
+    guarantee: AccountRef,
+    guarantor: AccountRef,
+}
+
+impl TaskOwner {
+    fn contains(&self, account: &AccountRef) -> bool {
+        &self.guarantee == account || &self.guarantor == account
+    }
+}
+
+struct TaskResult {
+    state: IpwisTaskState,
+    poll: TaskPoll,
+    expiration: Instant,
 }
 
 impl Kernel {
@@ -35,39 +105,513 @@ impl Kernel {
             },
         );
 
+        // load the capabilities granted to all accounts
+        // note: nothing is granted if not given, and `*` grants all capabilities
+        let capabilities: String = infer("ipwis_kernel_capabilities").unwrap_or_default();
+        let policy = match capabilities.trim() {
+            "*" => {
+                warn!("all capabilities are granted to all accounts");
+                CapabilityPolicy::with_default(None)
+            }
+            capabilities => {
+                if capabilities.is_empty() {
+                    warn!("no capabilities are granted; see `ipwis_kernel_capabilities`");
+                }
+                CapabilityPolicy::with_default(Some(
+                    capabilities
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|capability| !capability.is_empty())
+                        .map(ToString::to_string)
+                        .collect(),
+                ))
+            }
+        };
+
+        // load the retention window of the results
+        let result_ttl = Duration::from_millis(
+            infer("ipwis_kernel_result_ttl_ms").unwrap_or(DEFAULT_RESULT_TTL_MS),
+        );
+
+        // prepare a task table
+        let tasks = Arc::new_cyclic(|this| TaskTable {
+            this: this.clone(),
+            // note: the task ids should not be guessable
+            instances: Mutex::new(ResourceStore::with_random_ids()),
+            owners: Default::default(),
+            results: Default::default(),
+            result_ttl,
+        });
+
+        // register the sub-task module, which shares the tasks and programs
+        // note: the module does not own the tasks not to make a cycle
+        let programs: Arc<ProgramCache> = Default::default();
+        let registry: Weak<dyn SubtaskRegistry> = Arc::downgrade(&tasks);
+        manager
+            .interrupt_manager
+            .put(SubtaskModule::new(registry, programs.clone()))
+            .await?;
+
         Ok(Self {
             manager,
-            instances: Default::default(),
+            policy: Mutex::new(policy),
+            programs,
+            tasks,
         })
     }
 
+    // note: the hit/miss counters of the compiled modules can be read from this
+    pub fn module_cache(&self) -> &ModuleCache {
+        &self.manager.module_cache
+    }
+
     pub async fn spawn_raw(
         &self,
         task: Data<GuarantorSigned, Task>,
         program: &<IpwisTaskManager as TaskManager>::Program,
     ) -> Result<ResourceId> {
+        let owner = TaskOwner {
+            guarantee: task.metadata.guarantee.account,
+            guarantor: task.metadata.guarantor,
+        };
+        let mut capabilities = vec![];
+        task_graph::collect_capabilities(&task, &mut capabilities);
+
+        self.spawn_with(owner, &capabilities, self.manager.spawn_raw(task, program))
+            .await
+    }
+
+    /// # Safety
+    ///
+    /// The artifact is loaded as a native code without any validation,
+    /// so it should be made by [`Kernel::precompile`] on a trusted node.
+    pub async unsafe fn spawn_precompiled(
+        &self,
+        task: Data<GuarantorSigned, Task>,
+        source: &Path,
+        artifact: &[u8],
+    ) -> Result<ResourceId> {
+        let owner = TaskOwner {
+            guarantee: task.metadata.guarantee.account,
+            guarantor: task.metadata.guarantor,
+        };
+        let mut capabilities = vec![];
+        task_graph::collect_capabilities(&task, &mut capabilities);
+
+        self.spawn_with(
+            owner,
+            &capabilities,
+            self.manager.spawn_precompiled(task, source, artifact),
+        )
+        .await
+    }
+
+    pub async fn spawn_graph(
+        &self,
+        task: Data<GuarantorSigned, Task>,
+        programs: TaskPrograms,
+    ) -> Result<ResourceId> {
+        let owner = TaskOwner {
+            guarantee: task.metadata.guarantee.account,
+            guarantor: task.metadata.guarantor,
+        };
+        let mut capabilities = vec![];
+        task_graph::collect_capabilities(&task, &mut capabilities);
+
+        // validate the graph before running any task
+        task_graph::validate(&task)?;
+
+        let instance = task_graph::spawn(self.manager.clone(), task, Arc::new(programs));
+        self.spawn_with(owner, &capabilities, instance).await
+    }
+
+    pub async fn spawn_pipeline(
+        &self,
+        pipeline: Data<GuarantorSigned, TaskPipeline>,
+        programs: TaskPrograms,
+    ) -> Result<ResourceId> {
+        let owner = TaskOwner {
+            guarantee: pipeline.metadata.guarantee.account,
+            guarantor: pipeline.metadata.guarantor,
+        };
+        let mut capabilities = vec![];
+        task_pipeline::collect_capabilities(&pipeline, &mut capabilities);
+
+        let instance = task_pipeline::spawn(self.manager.clone(), pipeline, Arc::new(programs));
+        self.spawn_with(owner, &capabilities, instance).await
+    }
+
+    async fn spawn_with(
+        &self,
+        owner: TaskOwner,
+        capabilities: &[String],
+        instance: impl Future<Output = Result<IpwisTaskInstance>>,
+    ) -> Result<ResourceId> {
+        // check the requested capabilities of all tasks
+        // note: the task is not spawned until the future is polled
+        self.policy
+            .lock()
+            .await
+            .check(&owner.guarantee, capabilities)?;
+
         // spawn a task
-        let instance = self.manager.spawn_raw(task, program).await?;
+        let instance = instance.await?;
 
         // register as a resource
-        Ok(self.instances.lock().await.put(instance))
+        Ok(self.tasks.put(instance, owner).await)
     }
 
-    pub async fn poll(&self, id: &ResourceId) -> Result<TaskPoll> {
-        let instances = self.instances.lock().await;
-        if instances.get(id)?.handler.is_finished() {
-            drop(instances);
+    pub async fn precompile(
+        &self,
+        program: &<IpwisTaskManager as TaskManager>::Program,
+    ) -> Result<Vec<u8>> {
+        self.manager.precompile(program).await
+    }
+
+    pub async fn poll(&self, id: &ResourceId, account: &AccountRef) -> Result<TaskPoll> {
+        self.authorize(id, account).await?;
+        self.tasks.collect(id).await
+    }
+
+    pub async fn wait_timeout(
+        &self,
+        id: &ResourceId,
+        timeout: Option<Duration>,
+        account: &AccountRef,
+    ) -> Result<TaskPoll> {
+        self.authorize(id, account).await?;
+
+        self.tasks.wait_timeout(id, timeout).await
+    }
+
+    pub async fn kill(&self, id: &ResourceId, account: &AccountRef) -> Result<()> {
+        self.authorize(id, account).await?;
+        self.tasks.kill(id).await
+    }
+
+    pub async fn list(&self, account: &AccountRef) -> Result<Vec<TaskInfo>> {
+        // collect the finished tasks
+        let finished: Vec<_> = {
+            let instances = self.tasks.instances.lock().await;
+            instances
+                .iter()
+                .filter(|(_, instance)| instance.is_finished())
+                .map(|(id, _)| *id)
+                .collect()
+        };
+        for id in &finished {
+            // note: the task may be released concurrently
+            let _ = self.tasks.collect(id).await;
+        }
 
-            match self.wait(id).await {
-                Ok(outputs) => Ok(TaskPoll::Ready(outputs)),
-                Err(errors) => Ok(TaskPoll::Trap(Text::with_en_us(errors))),
+        let mut states: Vec<(ResourceId, IpwisTaskState, TaskStatus)> = vec![];
+        {
+            let mut results = self.tasks.results.lock().await;
+            self.tasks.purge(&mut results, Instant::now()).await;
+            for (id, result) in results.iter() {
+                states.push((*id, result.state.clone(), (&result.poll).into()));
             }
+        }
+        {
+            let instances = self.tasks.instances.lock().await;
+            for (id, instance) in instances.iter() {
+                states.push((*id, instance.state.clone(), TaskStatus::Running));
+            }
+        }
+
+        // note: an account can only see its own tasks
+        let owned: Vec<_> = {
+            let owners = self.tasks.owners.lock().await;
+            states
+                .into_iter()
+                .filter(
+                    |(id, _, _)| matches!(owners.get(id), Some(owner) if owner.contains(account)),
+                )
+                .collect()
+        };
+
+        let mut infos = vec![];
+        for (id, state, status) in owned {
+            infos.push(Self::info(id, &state, status).await);
+        }
+        infos.sort_by_key(|info| info.id);
+        Ok(infos)
+    }
+
+    pub async fn inspect(&self, id: &ResourceId, account: &AccountRef) -> Result<TaskInfo> {
+        self.authorize(id, account).await?;
+
+        // note: the finished task is collected into the results
+        let status = (&self.tasks.collect(id).await?).into();
+
+        let state = match self.tasks.results.lock().await.get(id) {
+            Some(result) => result.state.clone(),
+            None => self.tasks.instances.lock().await.get(id)?.state.clone(),
+        };
+
+        Ok(Self::info(*id, &state, status).await)
+    }
+
+    pub async fn logs(&self, id: &ResourceId, account: &AccountRef) -> Result<TaskLogs> {
+        self.authorize(id, account).await?;
+
+        let state = match self.tasks.results.lock().await.get(id) {
+            Some(result) => result.state.clone(),
+            None => self.tasks.instances.lock().await.get(id)?.state.clone(),
+        };
+
+        let mut logs = state.lock().await.logs.snapshot();
+        collect_logs(&state, None, &mut logs.children).await;
+        Ok(logs)
+    }
+
+    async fn authorize(&self, id: &ResourceId, account: &AccountRef) -> Result<()> {
+        match self.tasks.owners.lock().await.get(id) {
+            Some(owner) if owner.contains(account) => Ok(()),
+            Some(_) => bail!("permission denied: {id:x}"),
+            None => bail!("failed to find a resource: {id:x}"),
+        }
+    }
+
+    async fn info(id: ResourceId, state: &IpwisTaskState, status: TaskStatus) -> TaskInfo {
+        let state = state.lock().await;
+
+        TaskInfo {
+            id,
+            guarantee: state.task.metadata.guarantee.account,
+            program: state
+                .task
+                .program
+                .as_ref()
+                .map(|program| program.data.clone()),
+            created_date: state.created_date.clone(),
+            started_date: state.started_date.clone(),
+            finished_date: state.finished_date.clone(),
+            status,
+            fuel_consumed: state.fuel_consumed,
+            resources: state.resources.iter().cloned().collect(),
+            children: children(&state),
+        }
+    }
+
+    pub async fn release(&self, id: &ResourceId, account: &AccountRef) -> Result<()> {
+        self.authorize(id, account).await?;
+        self.tasks.release(id).await
+    }
+}
+
+impl TaskTable {
+    async fn put(&self, instance: IpwisTaskInstance, owner: TaskOwner) -> ResourceId {
+        let finished = instance.finished.clone();
+
+        let id = self.instances.lock().await.put(instance);
+        self.owners.lock().await.insert(id, owner);
+
+        // note: the result is retained from when the task is finished
+        tokio::spawn(Self::retain(self.this.clone(), id, finished));
+        id
+    }
+
+    async fn wait_timeout(&self, id: &ResourceId, timeout: Option<Duration>) -> Result<TaskPoll> {
+        // note: the instance is not locked while waiting
+        let finished = match self.instances.lock().await.get(id) {
+            Ok(instance) => instance.finished.clone(),
+            // the task may be already finished
+            Err(_) => return self.collect(id).await,
+        };
+        let finished = IpwisTaskInstance::wait_finished(finished);
+
+        match timeout {
+            // note: the pending tasks are reported by the collection
+            Some(timeout) => {
+                let _ = tokio::time::timeout(timeout, finished).await;
+            }
+            None => finished.await,
+        }
+        self.collect(id).await
+    }
+
+    async fn kill(&self, id: &ResourceId) -> Result<()> {
+        self.instances.lock().await.get(id)?.kill();
+        Ok(())
+    }
+
+    async fn release(&self, id: &ResourceId) -> Result<()> {
+        let mut results = self.results.lock().await;
+        if results.remove(id).is_some() {
+            self.owners.lock().await.remove(id);
+            return Ok(());
+        }
+
+        let mut instances = self.instances.lock().await;
+        if instances.get(id)?.is_finished() {
+            instances.remove(id)?;
+            self.owners.lock().await.remove(id);
+            Ok(())
+        } else {
+            bail!("task is still running: {id:x}")
+        }
+    }
+
+    async fn collect(&self, id: &ResourceId) -> Result<TaskPoll> {
+        // find the retained result
+        let mut results = self.results.lock().await;
+        let now = Instant::now();
+        self.purge(&mut results, now).await;
+        if let Some(result) = results.get(id) {
+            return Ok(result.poll.clone());
+        }
+
+        let mut instances = self.instances.lock().await;
+        let instance = instances.get(id)?;
+        if instance.is_finished() {
+            let instance = instances.remove(id)?;
+            let state = instance.state.clone();
+            drop(instances);
+
+            // retain the result
+            let poll = match instance.await {
+                Ok(outputs) => TaskPoll::Ready(outputs),
+                Err(error) => TaskPoll::Failed(error),
+            };
+            results.insert(
+                *id,
+                TaskResult {
+                    state,
+                    poll: poll.clone(),
+                    expiration: now + self.result_ttl,
+                },
+            );
+            Ok(poll)
         } else {
-            Ok(TaskPoll::Pending)
+            let state = instance.state.clone();
+            drop(instances);
+
+            let children = children(&*state.lock().await);
+            Ok(TaskPoll::Pending { children })
+        }
+    }
+
+    async fn purge(&self, results: &mut HashMap<ResourceId, TaskResult>, now: Instant) {
+        let expired: Vec<_> = results
+            .iter()
+            .filter(|(_, result)| result.expiration <= now)
+            .map(|(id, _)| *id)
+            .collect();
+        if expired.is_empty() {
+            return;
+        }
+
+        let mut owners = self.owners.lock().await;
+        for id in expired {
+            results.remove(&id);
+            owners.remove(&id);
+        }
+    }
+
+    // note: every finished task is swept into the results by this
+    async fn retain(tasks: Weak<Self>, id: ResourceId, finished: watch::Receiver<bool>) {
+        IpwisTaskInstance::wait_finished(finished).await;
+
+        // collect the result, which starts the TTL
+        // note: the task may be already released
+        let ttl = match tasks.upgrade() {
+            Some(tasks) => match tasks.collect(&id).await {
+                Ok(_) => tasks.result_ttl,
+                Err(_) => return,
+            },
+            None => return,
+        };
+
+        // purge the expired results
+        tokio::time::sleep(ttl).await;
+        if let Some(tasks) = tasks.upgrade() {
+            let mut results = tasks.results.lock().await;
+            tasks.purge(&mut results, Instant::now()).await;
+        }
+    }
+}
+
+fn children(state: &TaskState<IpwisTaskManager>) -> Vec<TaskChildInfo> {
+    state
+        .children
+        .iter()
+        .map(|(name, status)| TaskChildInfo {
+            name: name.clone(),
+            status: *status,
+        })
+        .collect()
+}
+
+// note: the members' logs are flattened with the dot-separated names
+fn collect_logs<'a>(
+    state: &'a IpwisTaskState,
+    prefix: Option<&'a str>,
+    logs: &'a mut Vec<TaskChildLogs>,
+) -> BoxFuture<'a, ()> {
+    async move {
+        let members: Vec<_> = state
+            .lock()
+            .await
+            .members
+            .iter()
+            .map(|(name, member)| (name.clone(), member.clone()))
+            .collect();
+
+        for (name, member) in members {
+            let name = match prefix {
+                Some(prefix) => format!("{prefix}.{name}"),
+                None => name,
+            };
+            let snapshot = member.lock().await.logs.snapshot();
+            logs.push(TaskChildLogs {
+                name: name.clone(),
+                stdout: snapshot.stdout,
+                stderr: snapshot.stderr,
+                dropped: snapshot.dropped,
+            });
+            collect_logs(&member, Some(&name), logs).await;
         }
     }
+    .boxed()
+}
+
+#[async_trait]
+impl SubtaskRegistry for TaskTable {
+    async fn put(&self, instance: IpwisTaskInstance) -> Result<ResourceId> {
+        // note: the sub-tasks are owned by the parent's owners
+        let owner = {
+            let state = instance.state.lock().await;
+            TaskOwner {
+                guarantee: state.task.metadata.guarantee.account,
+                guarantor: state.task.metadata.guarantor,
+            }
+        };
+        Ok(TaskTable::put(self, instance, owner).await)
+    }
+
+    async fn poll(&self, id: &ResourceId) -> Result<TaskPoll> {
+        self.collect(id).await
+    }
+
+    async fn wait(&self, id: &ResourceId) -> Result<TaskPoll> {
+        self.wait_timeout(id, None).await
+    }
 
-    pub async fn wait(&self, id: &ResourceId) -> Result<Box<ObjectData>> {
-        self.instances.lock().await.remove(id)?.await
+    async fn kill(&self, id: &ResourceId) -> Result<()> {
+        TaskTable::kill(self, id).await
+    }
+
+    async fn release(&self, id: &ResourceId) -> Result<()> {
+        // note: the killed sub-tasks are purged by the TTL if still running
+        let is_running = match self.instances.lock().await.get(id) {
+            Ok(instance) => !instance.is_finished(),
+            Err(_) => false,
+        };
+        if is_running {
+            TaskTable::kill(self, id).await
+        } else {
+            TaskTable::release(self, id).await
+        }
     }
 }
@@ -0,0 +1,347 @@
+use std::{collections::HashMap, sync::Arc};
+
+use ipis::{
+    class::Class,
+    core::{
+        account::GuarantorSigned,
+        anyhow::{bail, Error, Result},
+        data::Data,
+        value::{chrono::DateTime, hash::Hash},
+    },
+    futures::{
+        future::{join_all, BoxFuture},
+        FutureExt,
+    },
+    log::warn,
+    object::{data::ObjectData, value::ObjectValue, IntoObjectData},
+    tokio::{
+        self,
+        sync::{watch, Mutex, Notify},
+    },
+};
+use ipwis_modules_task_api::{
+    task_instance::TaskInstance, task_manager::TaskManager, task_state::TaskState,
+};
+use ipwis_modules_task_api_wasi::task_manager::IpwisTaskManager;
+use ipwis_modules_task_common::{
+    task::Task, task_error::TaskError, task_exception::TaskException, task_status::TaskStatus,
+};
+
+use crate::{IpwisTaskInstance, IpwisTaskState};
+
+pub type TaskPrograms = HashMap<Hash, Arc<Vec<u8>>>;
+
+/// The field name of the parent's outputs in the aggregated outputs.
+const PARENT_FIELD: &str = "self";
+
+// note: checks the graph statically, before spawning any task
+pub(crate) fn validate(task: &Task) -> Result<(), TaskError> {
+    for (name, child) in &task.children {
+        if name == PARENT_FIELD || name.is_empty() || name.contains('.') {
+            return Err(child_error(name, TaskError::kernel("invalid child name")));
+        }
+
+        // the child is fed the parent's outputs
+        task.constraints
+            .check_next(&child.constraints)
+            .and_then(|()| validate(child))
+            .map_err(|error| child_error(name, error))?;
+    }
+
+    for exception in &task.exceptions {
+        // the handler's outputs replace the task's outputs
+        if exception.constraints.outputs != task.constraints.outputs {
+            return Err(TaskError::TypeMismatch {
+                expected: format!("{:?}", &task.constraints.outputs.name),
+                found: format!("{:?}", &exception.constraints.outputs.name),
+            });
+        }
+        validate(exception)?;
+    }
+    Ok(())
+}
+
+pub(crate) fn collect_capabilities(task: &Task, capabilities: &mut Vec<String>) {
+    capabilities.extend(task.constraints.capabilities.iter().cloned());
+    for child in task
+        .children
+        .values()
+        .chain(&task.exceptions)
+        .chain(task.reserved.values())
+    {
+        collect_capabilities(child, capabilities);
+    }
+}
+
+// note: the parent's outputs are fed to the children's inputs,
+//       and the children's outputs are aggregated into the task's outputs.
+//       if any of them fails, the exception handlers are run instead
+pub(crate) fn spawn(
+    manager: Arc<IpwisTaskManager>,
+    task: Data<GuarantorSigned, Task>,
+    programs: Arc<TaskPrograms>,
+) -> BoxFuture<'static, Result<IpwisTaskInstance>> {
+    async move {
+        let program = match &task.program {
+            Some(program) => match programs.get(&program.data.value) {
+                Some(program) => program.clone(),
+                None => bail!("failed to find the program: {}", program.data.value),
+            },
+            None => bail!("Empty program"),
+        };
+
+        // spawn the parent
+        let parent = manager.spawn_raw(task.clone(), &program).await?;
+        if task.children.is_empty() && task.exceptions.is_empty() {
+            return Ok(parent);
+        }
+
+        // note: the graph has its own state, as the parent finishes before the children
+        let state = new_state(manager.clone(), task.clone());
+        state
+            .lock()
+            .await
+            .members
+            .insert(PARENT_FIELD.to_string(), parent.state.clone());
+
+        let kill_switch: Arc<Notify> = Default::default();
+        let (finished_tx, finished) = watch::channel(false);
+        let handler = {
+            let kill_switch = kill_switch.clone();
+            let state = state.clone();
+            tokio::spawn(async move {
+                let outputs = match run(&manager, &task, &programs, &state, parent, &kill_switch)
+                    .await
+                {
+                    Err(error) if error != TaskError::Cancelled && !task.exceptions.is_empty() => {
+                        handle(&manager, &task, &programs, &state, error, &kill_switch).await
+                    }
+                    outputs => outputs,
+                };
+                finish(&state).await;
+
+                // notify the waiters
+                let _ = finished_tx.send(true);
+                outputs
+            })
+        };
+
+        Ok(TaskInstance {
+            state,
+            handler,
+            kill_switch,
+            finished,
+        })
+    }
+    .boxed()
+}
+
+async fn run(
+    manager: &Arc<IpwisTaskManager>,
+    task: &Data<GuarantorSigned, Task>,
+    programs: &Arc<TaskPrograms>,
+    state: &IpwisTaskState,
+    parent: IpwisTaskInstance,
+    kill_switch: &Notify,
+) -> Result<Box<ObjectData>, TaskError> {
+    // wait for the parent
+    let outputs = wait(parent, kill_switch).await?;
+    if task.children.is_empty() {
+        return Ok(outputs);
+    }
+
+    // spawn the children
+    let mut names = vec![];
+    let mut children = vec![];
+    let mut entries: Vec<_> = task.children.iter().collect();
+    entries.sort_by_key(|(name, _)| *name);
+    for (name, child) in entries {
+        let mut child_task = task.clone();
+        child_task.data = child.clone();
+        child_task.data.constraints.inputs = (*outputs).clone();
+
+        match spawn(manager.clone(), child_task, programs.clone()).await {
+            Ok(child) => {
+                names.push(name.clone());
+                children.push(child);
+            }
+            Err(error) => {
+                for child in &children {
+                    child.kill();
+                }
+                return Err(child_error(name, spawn_error(error)));
+            }
+        }
+    }
+
+    // report the children's status to the graph
+    {
+        let mut state = state.lock().await;
+        for (name, child) in names.iter().zip(&children) {
+            state.children.insert(name.clone(), TaskStatus::Running);
+            state.members.insert(name.clone(), child.state.clone());
+        }
+    }
+
+    // wait for the children
+    let kill_switches: Vec<_> = children
+        .iter()
+        .map(|child| child.kill_switch.clone())
+        .collect();
+    let children = names.iter().zip(children).map(|(name, child)| {
+        let state = state.clone();
+        async move {
+            let result = child.await;
+            let status = match &result {
+                Ok(_) => TaskStatus::Ready,
+                Err(error) => error.into(),
+            };
+            state.lock().await.children.insert(name.clone(), status);
+            result
+        }
+    });
+    let results = tokio::select! {
+        results = join_all(children) => results,
+        () = kill_switch.notified() => {
+            for kill_switch in kill_switches {
+                kill_switch.notify_one();
+            }
+            return Err(TaskError::Cancelled);
+        }
+    };
+
+    // note: the parent's outputs are kept along with the children's
+    let mut fields = vec![(PARENT_FIELD.to_string(), outputs)];
+    for (name, result) in names.into_iter().zip(results) {
+        match result {
+            Ok(outputs) => fields.push((name, outputs)),
+            Err(error) => return Err(child_error(&name, error)),
+        }
+    }
+    Ok(Box::new(aggregate(fields)))
+}
+
+async fn handle(
+    manager: &Arc<IpwisTaskManager>,
+    task: &Data<GuarantorSigned, Task>,
+    programs: &Arc<TaskPrograms>,
+    state: &IpwisTaskState,
+    error: TaskError,
+    kill_switch: &Notify,
+) -> Result<Box<ObjectData>, TaskError> {
+    // note: the exception handlers are tried in order
+    let inputs = TaskException::from(&error).__into_object_data();
+    for (index, exception) in task.exceptions.iter().enumerate() {
+        let mut exception_task = task.clone();
+        exception_task.data = exception.clone();
+        exception_task.data.constraints.inputs = inputs.clone();
+        exception_task.data.constraints.inputs_class = Some(TaskException::__class_metadata());
+
+        let result = match spawn(manager.clone(), exception_task, programs.clone()).await {
+            Ok(instance) => {
+                state
+                    .lock()
+                    .await
+                    .members
+                    .insert(format!("exception[{index}]"), instance.state.clone());
+                wait(instance, kill_switch).await
+            }
+            Err(error) => Err(spawn_error(error)),
+        };
+        match result {
+            Ok(outputs) => return Ok(outputs),
+            Err(TaskError::Cancelled) => return Err(TaskError::Cancelled),
+            Err(error) => warn!("failed to handle the exception [{index}]: {error}"),
+        }
+    }
+
+    // report the original failure
+    Err(error)
+}
+
+pub(crate) fn new_state(
+    manager: Arc<IpwisTaskManager>,
+    task: Data<GuarantorSigned, Task>,
+) -> IpwisTaskState {
+    Arc::new(Mutex::new(TaskState {
+        manager,
+        task,
+        created_date: DateTime::now(),
+        started_date: Some(DateTime::now()),
+        finished_date: None,
+        fuel_consumed: None,
+        resources: Default::default(),
+        children: Default::default(),
+        members: Default::default(),
+        // note: the outputs are written by the members only
+        logs: Default::default(),
+    }))
+}
+
+// note: the fuel consumed by the members is summed up
+pub(crate) async fn finish(state: &IpwisTaskState) {
+    let mut state = state.lock().await;
+
+    let mut fuel_consumed = None;
+    for member in state.members.values() {
+        if let Some(fuel) = member.lock().await.fuel_consumed {
+            fuel_consumed = Some(fuel_consumed.unwrap_or(0u64).saturating_add(fuel));
+        }
+    }
+    state.fuel_consumed = fuel_consumed;
+    state.finished_date = Some(DateTime::now());
+}
+
+pub(crate) async fn wait(
+    instance: IpwisTaskInstance,
+    kill_switch: &Notify,
+) -> Result<Box<ObjectData>, TaskError> {
+    let instance_kill_switch = instance.kill_switch.clone();
+    tokio::select! {
+        outputs = instance => outputs,
+        () = kill_switch.notified() => {
+            instance_kill_switch.notify_one();
+            Err(TaskError::Cancelled)
+        }
+    }
+}
+
+// note: the structured errors, e.g. the rejections, are kept as they are
+pub(crate) fn spawn_error(error: Error) -> TaskError {
+    error.downcast().unwrap_or_else(TaskError::kernel)
+}
+
+fn child_error(name: &str, error: TaskError) -> TaskError {
+    match error {
+        TaskError::Child {
+            name: child,
+            status,
+            message,
+        } => TaskError::Child {
+            name: format!("{name}.{child}"),
+            status,
+            message,
+        },
+        error => TaskError::Child {
+            name: name.to_string(),
+            status: (&error).into(),
+            message: error.to_string(),
+        },
+    }
+}
+
+fn aggregate(fields: Vec<(String, Box<ObjectData>)>) -> ObjectData {
+    // note: each field is named after the child task, or `PARENT_FIELD`
+    ObjectData {
+        value: ObjectValue::Object(
+            fields
+                .into_iter()
+                .map(|(name, outputs)| ObjectData {
+                    name: name.into(),
+                    ..*outputs
+                })
+                .collect(),
+        ),
+        ..().__into_object_data()
+    }
+}
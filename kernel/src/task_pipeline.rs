@@ -0,0 +1,169 @@
+use std::sync::Arc;
+
+use ipis::{
+    core::{
+        account::GuarantorSigned,
+        anyhow::{bail, Result},
+        data::Data,
+    },
+    object::data::ObjectData,
+    tokio::{
+        self,
+        sync::{watch, Notify},
+    },
+};
+use ipwis_modules_task_api::{task_instance::TaskInstance, task_manager::TaskManager};
+use ipwis_modules_task_api_wasi::task_manager::IpwisTaskManager;
+use ipwis_modules_task_common::{
+    task::Task, task_error::TaskError, task_pipeline::TaskPipeline, task_status::TaskStatus,
+};
+
+use crate::{
+    task_graph::{self, TaskPrograms},
+    IpwisTaskInstance, IpwisTaskState,
+};
+
+pub(crate) fn collect_capabilities(pipeline: &TaskPipeline, capabilities: &mut Vec<String>) {
+    for stage in &pipeline.stages {
+        capabilities.extend(stage.constraints.capabilities.iter().cloned());
+    }
+}
+
+// note: the stages are run one by one, and only the last stage's outputs
+//       are returned. the state of the instance follows the running stage
+pub(crate) async fn spawn(
+    manager: Arc<IpwisTaskManager>,
+    pipeline: Data<GuarantorSigned, TaskPipeline>,
+    programs: Arc<TaskPrograms>,
+) -> Result<IpwisTaskInstance> {
+    if pipeline.stages.is_empty() {
+        bail!("Empty pipeline");
+    }
+
+    // validate the chain before running any stage
+    for (index, stages) in pipeline.stages.windows(2).enumerate() {
+        stages[0]
+            .constraints
+            .check_next(&stages[1].constraints)
+            .map_err(|error| stage_error(index + 1, error))?;
+    }
+
+    // spawn the first stage
+    let first = spawn_stage(&manager, &pipeline, &programs, 0, None).await?;
+
+    // note: the pipeline has its own state, as the stages finish one by one
+    let task = first.state.lock().await.task.clone();
+    let state = task_graph::new_state(manager.clone(), task);
+    track(&state, 0, &first).await;
+
+    let kill_switch: Arc<Notify> = Default::default();
+    let (finished_tx, finished) = watch::channel(false);
+    let handler = {
+        let kill_switch = kill_switch.clone();
+        let state = state.clone();
+        tokio::spawn(async move {
+            let outputs = run(&manager, &pipeline, &programs, &state, first, &kill_switch).await;
+            task_graph::finish(&state).await;
+
+            // notify the waiters
+            let _ = finished_tx.send(true);
+            outputs
+        })
+    };
+
+    Ok(TaskInstance {
+        state,
+        handler,
+        kill_switch,
+        finished,
+    })
+}
+
+async fn run(
+    manager: &IpwisTaskManager,
+    pipeline: &Data<GuarantorSigned, TaskPipeline>,
+    programs: &TaskPrograms,
+    state: &IpwisTaskState,
+    first: IpwisTaskInstance,
+    kill_switch: &Notify,
+) -> Result<Box<ObjectData>, TaskError> {
+    let mut instance = first;
+    let mut index = 0;
+    loop {
+        let result = task_graph::wait(instance, kill_switch).await;
+
+        // report the stage's status to the pipeline
+        let status = match &result {
+            Ok(_) => TaskStatus::Ready,
+            Err(error) => error.into(),
+        };
+        state
+            .lock()
+            .await
+            .children
+            .insert(index.to_string(), status);
+
+        let outputs = match result {
+            Ok(outputs) => outputs,
+            Err(TaskError::Cancelled) => return Err(TaskError::Cancelled),
+            Err(error) => return Err(stage_error(index, error)),
+        };
+
+        // feed the outputs to the next stage
+        index += 1;
+        if index == pipeline.stages.len() {
+            break Ok(outputs);
+        }
+        instance = spawn_stage(manager, pipeline, programs, index, Some(*outputs))
+            .await
+            .map_err(|error| stage_error(index, task_graph::spawn_error(error)))?;
+        track(state, index, &instance).await;
+    }
+}
+
+// note: the stages are named after their indices
+async fn track(state: &IpwisTaskState, index: usize, instance: &IpwisTaskInstance) {
+    let task = instance.state.lock().await.task.clone();
+
+    let mut state = state.lock().await;
+    state.task = task;
+    state
+        .children
+        .insert(index.to_string(), TaskStatus::Running);
+    state
+        .members
+        .insert(index.to_string(), instance.state.clone());
+}
+
+async fn spawn_stage(
+    manager: &IpwisTaskManager,
+    pipeline: &Data<GuarantorSigned, TaskPipeline>,
+    programs: &TaskPrograms,
+    index: usize,
+    inputs: Option<ObjectData>,
+) -> Result<IpwisTaskInstance> {
+    let stage = &pipeline.stages[index];
+    let program = match programs.get(&stage.program.data.value) {
+        Some(program) => program,
+        None => bail!("failed to find the program: {}", stage.program.data.value),
+    };
+
+    // note: the stages are signed as the pipeline
+    let mut task: Data<GuarantorSigned, Task> = Data {
+        metadata: pipeline.metadata.clone(),
+        data: stage.to_task(),
+    };
+    if let Some(inputs) = inputs {
+        task.data.constraints.inputs = inputs;
+    }
+
+    manager.spawn_raw(task, program).await
+}
+
+fn stage_error(index: usize, error: TaskError) -> TaskError {
+    TaskError::Stage {
+        index: index as u32,
+        status: (&error).into(),
+        message: error.to_string(),
+    }
+}
@@ -13,19 +13,26 @@ use rkyv::{Archive, Deserialize, Serialize};
 
 pub struct ResourceStore<R> {
     map: HashMap<ResourceId, R>,
-    seed: ResourceId,
+    seed: Option<ResourceId>,
 }
 
 impl<R> Default for ResourceStore<R> {
     fn default() -> Self {
         Self {
             map: Default::default(),
-            seed: ResourceId::zero(),
+            seed: Some(ResourceId::zero()),
         }
     }
 }
 
 impl<R> ResourceStore<R> {
+    pub fn with_random_ids() -> Self {
+        Self {
+            map: Default::default(),
+            seed: None,
+        }
+    }
+
     pub fn get(&self, id: &ResourceId) -> Result<&R> {
         self.map
             .get(id)
@@ -38,8 +45,20 @@ impl<R> ResourceStore<R> {
             .ok_or_else(|| anyhow!("failed to find a resource: {id:x}"))
     }
 
+    pub fn iter(&self) -> impl Iterator<Item = (&ResourceId, &R)> {
+        self.map.iter()
+    }
+
     pub fn put(&mut self, value: R) -> ResourceId {
-        let id = self.seed.next();
+        let id = match &mut self.seed {
+            Some(seed) => seed.next(),
+            None => loop {
+                let id = ResourceId::random();
+                if !self.map.contains_key(&id) {
+                    break id;
+                }
+            },
+        };
         self.map.insert(id, value);
         id
     }
@@ -96,6 +115,10 @@ impl ResourceId {
         Self(Default::default())
     }
 
+    fn random() -> Self {
+        Self(::rand::random())
+    }
+
     fn next(&mut self) -> Self {
         let current = *self;
         self.0 += 1;
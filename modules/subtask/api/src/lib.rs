@@ -0,0 +1,338 @@
+#![allow(clippy::missing_safety_doc)]
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Weak},
+};
+
+use ipis::{
+    async_trait::async_trait,
+    core::{
+        anyhow::{anyhow, bail, Result},
+        signed::IsSigned,
+    },
+    env::{infer, Infer},
+    log::warn,
+    object::data::ObjectData,
+    pin::PinnedInner,
+    resource::Resource,
+    rkyv::AlignedVec,
+    tokio::sync::{watch, Mutex},
+};
+use ipsis_common::Ipsis;
+use ipwis_modules_core_common::resource_store::ResourceId;
+use ipwis_modules_subtask_common::{io, ExternTask};
+use ipwis_modules_task_api::{
+    task_instance::TaskInstance, task_manager::TaskManager, task_state::TaskState,
+};
+use ipwis_modules_task_api_wasi::{
+    interrupt_handler::InterruptHandler, interrupt_module::InterruptModule, memory::IpwisMemory,
+    program_cache::ProgramCache, task_manager::IpwisTaskManager,
+};
+use ipwis_modules_task_common::{
+    task::Task, task_constraints::TaskConstraints, task_poll::TaskPoll,
+    task_resource_constraints::TaskResourceConstraints,
+};
+use ipwis_modules_task_common_wasi::interrupt_id::InterruptId;
+
+pub type IpwisTaskInstance = TaskInstance<Box<ObjectData>, IpwisTaskManager>;
+type IpwisTaskState = Arc<Mutex<TaskState<IpwisTaskManager>>>;
+
+/// The default maximum number of the running sub-tasks of each task.
+const DEFAULT_MAX_TASKS: usize = 16;
+
+// note: the sub-tasks are kept by the kernel so that they can be listed
+#[async_trait]
+pub trait SubtaskRegistry: Send + Sync {
+    async fn put(&self, instance: IpwisTaskInstance) -> Result<ResourceId>;
+
+    async fn poll(&self, id: &ResourceId) -> Result<TaskPoll>;
+
+    async fn wait(&self, id: &ResourceId) -> Result<TaskPoll>;
+
+    async fn kill(&self, id: &ResourceId) -> Result<()>;
+
+    async fn release(&self, id: &ResourceId) -> Result<()>;
+}
+
+pub struct SubtaskModule {
+    registry: Weak<dyn SubtaskRegistry>,
+    programs: Arc<ProgramCache>,
+}
+
+impl SubtaskModule {
+    pub fn new(registry: Weak<dyn SubtaskRegistry>, programs: Arc<ProgramCache>) -> Self {
+        Self { registry, programs }
+    }
+}
+
+#[async_trait]
+impl InterruptModule for SubtaskModule {
+    fn id(&self) -> InterruptId {
+        io::OpCode::ID
+    }
+
+    async fn spawn_handler(&self) -> Result<Box<dyn InterruptHandler>> {
+        bail!("the subtask module requires the caller task")
+    }
+
+    async fn spawn_handler_for(&self, state: &IpwisTaskState) -> Result<Box<dyn InterruptHandler>> {
+        Ok(Box::new(SubtaskHandler {
+            parent: state.clone(),
+            registry: self.registry.clone(),
+            programs: self.programs.clone(),
+            client: None,
+            tasks: Default::default(),
+            max_tasks: infer("ipwis_subtask_max_tasks").unwrap_or(DEFAULT_MAX_TASKS),
+        }))
+    }
+}
+
+pub struct SubtaskHandler {
+    parent: IpwisTaskState,
+    registry: Weak<dyn SubtaskRegistry>,
+    programs: Arc<ProgramCache>,
+    client: Option<::ipiis_api::client::IpiisClient>,
+    // note: the guest can access the sub-tasks spawned by itself only
+    tasks: HashMap<ResourceId, watch::Receiver<bool>>,
+    max_tasks: usize,
+}
+
+#[async_trait]
+impl InterruptHandler for SubtaskHandler {
+    async unsafe fn handle_raw(
+        &mut self,
+        memory: &mut IpwisMemory,
+        inputs: &[u8],
+    ) -> Result<AlignedVec> {
+        match PinnedInner::deserialize_owned(inputs)? {
+            io::OpCode::Spawn(req) => self
+                .handle_spawn(memory, *req)
+                .await?
+                .to_bytes()
+                .map_err(Into::into),
+            io::OpCode::SpawnReserved(req) => self
+                .handle_spawn_reserved(memory, *req)
+                .await?
+                .to_bytes()
+                .map_err(Into::into),
+            io::OpCode::Poll(req) => self.handle_poll(req).await?.to_bytes().map_err(Into::into),
+            io::OpCode::Wait(req) => self.handle_wait(req).await?.to_bytes().map_err(Into::into),
+            io::OpCode::Kill(req) => self.handle_kill(req).await?.to_bytes().map_err(Into::into),
+            io::OpCode::Release(req) => self
+                .handle_release(req)
+                .await?
+                .to_bytes()
+                .map_err(Into::into),
+        }
+    }
+}
+
+#[async_trait]
+impl Resource for SubtaskHandler {
+    async fn release(&mut self) -> Result<()> {
+        // note: the running sub-tasks do not outlive the handles
+        // note: releasing a running sub-task kills it
+        let registry = match self.registry.upgrade() {
+            Some(registry) => registry,
+            // note: the sub-tasks are gone with the kernel
+            None => {
+                self.tasks.clear();
+                return Ok(());
+            }
+        };
+        for (id, _) in self.tasks.drain() {
+            if let Err(error) = registry.release(&id).await {
+                warn!("failed to release the sub-task: {id:x}: {error}");
+            }
+        }
+        Ok(())
+    }
+}
+
+impl SubtaskHandler {
+    fn registry(&self) -> Result<Arc<dyn SubtaskRegistry>> {
+        self.registry
+            .upgrade()
+            .ok_or_else(|| anyhow!("the kernel has been shut down"))
+    }
+
+    fn get(&self, id: &ResourceId) -> Result<Arc<dyn SubtaskRegistry>> {
+        if self.tasks.contains_key(id) {
+            self.registry()
+        } else {
+            bail!("failed to find a resource: {id:x}")
+        }
+    }
+
+    async fn spawn(&mut self, task: Task) -> Result<ExternTask> {
+        // note: the sub-tasks are bounded by count, as their memory is not metered
+        let running = self
+            .tasks
+            .values()
+            .filter(|finished| !*finished.borrow())
+            .count();
+        if running >= self.max_tasks {
+            bail!("too many running sub-tasks: {running}");
+        }
+
+        // note: the sub-tasks are signed as the parent
+        let (manager, task) = {
+            let parent = self.parent.lock().await;
+            let mut child = parent.task.clone();
+            child.data = task;
+            (parent.manager.clone(), child)
+        };
+
+        // fetch the program
+        let program = match &task.program {
+            Some(program) => program,
+            None => bail!("Empty program"),
+        };
+        let bytes = match self.programs.get(&program.data).await {
+            Some(bytes) => bytes,
+            None => {
+                let client = match &mut self.client {
+                    Some(client) => client,
+                    None => self
+                        .client
+                        .insert(::ipiis_api::client::IpiisClient::try_infer().await?),
+                };
+                let bytes = client.get(program).await?;
+                self.programs.put(&program.data, bytes).await?
+            }
+        };
+
+        // spawn a task
+        let registry = self.registry()?;
+        let instance = manager.spawn_raw(task, &bytes).await?;
+        let finished = instance.finished.clone();
+
+        let id = registry.put(instance).await?;
+        self.tasks.insert(id, finished);
+        Ok(ExternTask::new(id))
+    }
+
+    async fn handle_spawn(
+        &mut self,
+        memory: &mut IpwisMemory,
+        req: io::request::Spawn,
+    ) -> Result<io::response::Spawn> {
+        // note: the sub-tasks cannot exceed the parent's constraints
+        let constraints = {
+            let parent = self.parent.lock().await;
+            let constraints = &parent.task.constraints;
+
+            // note: the unreserved programs need an explicit grant
+            if !constraints
+                .capabilities
+                .iter()
+                .any(|capability| capability == io::SPAWN_CAPABILITY)
+            {
+                bail!("capability not granted: {}", io::SPAWN_CAPABILITY);
+            }
+
+            let capabilities = match req.capabilities {
+                Some(capabilities) => {
+                    if let Some(capability) = capabilities
+                        .iter()
+                        .find(|&capability| !constraints.capabilities.contains(capability))
+                    {
+                        bail!("capability not granted: {capability}");
+                    }
+                    capabilities
+                }
+                None => constraints.capabilities.clone(),
+            };
+
+            TaskConstraints {
+                inputs: req.inputs,
+                inputs_class: None,
+                outputs: req.outputs,
+                resources: req.resources.unwrap_or(TaskResourceConstraints::UNLIMITED),
+                capabilities,
+                wasi: Default::default(),
+            }
+        };
+
+        self.spawn_narrowed(
+            memory,
+            Task {
+                constraints,
+                program: Some(req.program),
+                ..Task::new_sandbox()
+            },
+        )
+        .await
+    }
+
+    async fn handle_spawn_reserved(
+        &mut self,
+        memory: &mut IpwisMemory,
+        req: io::request::SpawnReserved,
+    ) -> Result<io::response::SpawnReserved> {
+        // note: only the tasks reserved in the signed parent task can be spawned
+        let mut task = match self.parent.lock().await.task.reserved.get(&req.name) {
+            Some(task) => task.clone(),
+            None => bail!("failed to find the reserved task: {}", &req.name),
+        };
+        task.constraints.inputs = req.inputs;
+
+        self.spawn_narrowed(memory, task).await
+    }
+
+    // note: the sub-tasks cannot exceed the parent's resources,
+    //       and their fuel is taken from the parent's remaining fuel
+    async fn spawn_narrowed(
+        &mut self,
+        memory: &mut IpwisMemory,
+        mut task: Task,
+    ) -> Result<ExternTask> {
+        task.constraints.resources = {
+            let parent = self.parent.lock().await;
+            let resources = &parent.task.constraints.resources;
+
+            let remaining = TaskResourceConstraints {
+                fuel: resources.fuel.map(|fuel| {
+                    fuel.saturating_sub(memory.store.fuel_consumed().unwrap_or_default())
+                }),
+                ..resources.clone()
+            };
+            remaining.narrow(&task.constraints.resources)
+        };
+
+        // take the fuel from the parent
+        let fuel = task.constraints.resources.fuel;
+        if let Some(fuel) = fuel {
+            memory.store.consume_fuel(fuel)?;
+        }
+
+        let result = self.spawn(task).await;
+
+        // give the fuel back if failed
+        if let (Err(_), Some(fuel)) = (&result, fuel) {
+            memory.store.add_fuel(fuel)?;
+        }
+        result
+    }
+
+    async fn handle_poll(&mut self, req: io::request::Poll) -> Result<io::response::Poll> {
+        self.get(&req.id)?.poll(&req.id).await
+    }
+
+    async fn handle_wait(&mut self, req: io::request::Wait) -> Result<io::response::Wait> {
+        self.get(&req.id)?.wait(&req.id).await
+    }
+
+    async fn handle_kill(&mut self, req: io::request::Kill) -> Result<io::response::Kill> {
+        self.get(&req.id)?.kill(&req.id).await
+    }
+
+    async fn handle_release(&mut self, req: io::request::Release) -> Result<io::response::Release> {
+        let registry = self.get(&req.id)?;
+        self.tasks.remove(&req.id);
+
+        // note: releasing a running sub-task kills it
+        registry.release(&req.id).await
+    }
+}
@@ -0,0 +1,245 @@
+use bytecheck::CheckBytes;
+use ipis::{
+    class::metadata::ClassMetadata,
+    core::{account::GuarantorSigned, data::Data, signed::IsSigned},
+    object::data::ObjectData,
+    path::Path,
+};
+#[cfg(target_os = "wasi")]
+use ipis::{core::anyhow::Result, log::warn};
+use ipwis_modules_core_common::resource_store::ResourceId;
+use ipwis_modules_task_common::{
+    task_poll::TaskPoll, task_resource_constraints::TaskResourceConstraints,
+};
+#[cfg(target_os = "wasi")]
+use rkyv::{de::deserializers::SharedDeserializeMap, validation::validators::DefaultValidator};
+use rkyv::{Archive, Deserialize, Serialize};
+
+#[derive(Archive, Serialize, Deserialize)]
+#[archive_attr(derive(CheckBytes))]
+#[allow(dead_code)]
+pub struct ExternTask {
+    id: ResourceId,
+}
+
+impl IsSigned for ExternTask {}
+
+#[cfg(not(target_os = "wasi"))]
+impl ExternTask {
+    pub fn new(id: ResourceId) -> Self {
+        Self { id }
+    }
+}
+
+#[cfg(target_os = "wasi")]
+impl ExternTask {
+    pub fn spawn(
+        program: Data<GuarantorSigned, Path>,
+        inputs: ObjectData,
+        outputs: ClassMetadata,
+    ) -> Result<Self> {
+        Self::spawn_narrowed(program, inputs, outputs, None, None)
+    }
+
+    pub fn spawn_narrowed(
+        program: Data<GuarantorSigned, Path>,
+        inputs: ObjectData,
+        outputs: ClassMetadata,
+        resources: Option<TaskResourceConstraints>,
+        capabilities: Option<Vec<String>>,
+    ) -> Result<Self> {
+        unsafe {
+            io::request::Spawn {
+                program,
+                inputs,
+                outputs,
+                resources,
+                capabilities,
+            }
+            .syscall()
+        }
+    }
+
+    pub fn spawn_reserved(name: impl ToString, inputs: ObjectData) -> Result<Self> {
+        unsafe {
+            io::request::SpawnReserved {
+                name: name.to_string(),
+                inputs,
+            }
+            .syscall()
+        }
+    }
+
+    pub fn poll(&self) -> Result<TaskPoll> {
+        unsafe { io::request::Poll { id: self.id }.syscall() }
+    }
+
+    pub fn wait(&self) -> Result<TaskPoll> {
+        unsafe { io::request::Wait { id: self.id }.syscall() }
+    }
+
+    pub fn kill(&self) -> Result<()> {
+        unsafe { io::request::Kill { id: self.id }.syscall() }
+    }
+}
+
+#[cfg(target_os = "wasi")]
+impl Drop for ExternTask {
+    fn drop(&mut self) {
+        if let Err(error) = unsafe { io::request::Release { id: self.id }.syscall() } {
+            warn!("failed to release the ExternTask: {:x}: {error}", self.id);
+        }
+    }
+}
+
+pub mod io {
+    use ipwis_modules_task_common_wasi::interrupt_id::InterruptId;
+
+    use super::*;
+
+    /// The capability to spawn the programs which are not reserved by the parent task.
+    pub const SPAWN_CAPABILITY: &str = "ipwis_modules_subtask_spawn";
+
+    #[derive(Archive, Serialize, Deserialize)]
+    #[archive_attr(derive(CheckBytes))]
+    pub enum OpCode {
+        Spawn(Box<self::request::Spawn>),
+        SpawnReserved(Box<self::request::SpawnReserved>),
+        Poll(self::request::Poll),
+        Wait(self::request::Wait),
+        Kill(self::request::Kill),
+        Release(self::request::Release),
+    }
+
+    impl IsSigned for OpCode {}
+
+    impl OpCode {
+        pub const ID: InterruptId = InterruptId("ipwis_modules_subtask");
+
+        #[cfg(target_os = "wasi")]
+        unsafe fn syscall<O>(mut self) -> Result<O>
+        where
+            O: Archive,
+            <O as Archive>::Archived:
+                for<'a> CheckBytes<DefaultValidator<'a>> + Deserialize<O, SharedDeserializeMap>,
+        {
+            Self::ID.syscall(&mut self)
+        }
+    }
+
+    pub mod request {
+        use super::*;
+
+        #[derive(Archive, Serialize, Deserialize)]
+        #[archive_attr(derive(CheckBytes))]
+        pub struct Spawn {
+            pub program: Data<GuarantorSigned, Path>,
+            pub inputs: ObjectData,
+            pub outputs: ClassMetadata,
+            // note: the parent's constraints are inherited if not given
+            pub resources: Option<TaskResourceConstraints>,
+            pub capabilities: Option<Vec<String>>,
+        }
+
+        impl IsSigned for Spawn {}
+
+        #[cfg(target_os = "wasi")]
+        impl Spawn {
+            pub(crate) unsafe fn syscall(self) -> Result<super::response::Spawn> {
+                super::OpCode::Spawn(Box::new(self)).syscall()
+            }
+        }
+
+        #[derive(Archive, Serialize, Deserialize)]
+        #[archive_attr(derive(CheckBytes))]
+        pub struct SpawnReserved {
+            pub name: String,
+            pub inputs: ObjectData,
+        }
+
+        impl IsSigned for SpawnReserved {}
+
+        #[cfg(target_os = "wasi")]
+        impl SpawnReserved {
+            pub(crate) unsafe fn syscall(self) -> Result<super::response::SpawnReserved> {
+                super::OpCode::SpawnReserved(Box::new(self)).syscall()
+            }
+        }
+
+        #[derive(Archive, Serialize, Deserialize)]
+        #[archive_attr(derive(CheckBytes))]
+        pub struct Poll {
+            pub id: ResourceId,
+        }
+
+        impl IsSigned for Poll {}
+
+        #[cfg(target_os = "wasi")]
+        impl Poll {
+            pub(crate) unsafe fn syscall(self) -> Result<super::response::Poll> {
+                super::OpCode::Poll(self).syscall()
+            }
+        }
+
+        #[derive(Archive, Serialize, Deserialize)]
+        #[archive_attr(derive(CheckBytes))]
+        pub struct Wait {
+            pub id: ResourceId,
+        }
+
+        impl IsSigned for Wait {}
+
+        #[cfg(target_os = "wasi")]
+        impl Wait {
+            pub(crate) unsafe fn syscall(self) -> Result<super::response::Wait> {
+                super::OpCode::Wait(self).syscall()
+            }
+        }
+
+        #[derive(Archive, Serialize, Deserialize)]
+        #[archive_attr(derive(CheckBytes))]
+        pub struct Kill {
+            pub id: ResourceId,
+        }
+
+        impl IsSigned for Kill {}
+
+        #[cfg(target_os = "wasi")]
+        impl Kill {
+            pub(crate) unsafe fn syscall(self) -> Result<super::response::Kill> {
+                super::OpCode::Kill(self).syscall()
+            }
+        }
+
+        #[derive(Archive, Serialize, Deserialize)]
+        #[archive_attr(derive(CheckBytes))]
+        pub struct Release {
+            pub id: ResourceId,
+        }
+
+        impl IsSigned for Release {}
+
+        #[cfg(target_os = "wasi")]
+        impl Release {
+            pub(crate) unsafe fn syscall(self) -> Result<super::response::Release> {
+                super::OpCode::Release(self).syscall()
+            }
+        }
+    }
+
+    pub mod response {
+        use super::*;
+
+        pub type Spawn = ExternTask;
+
+        pub type SpawnReserved = ExternTask;
+
+        pub type Poll = TaskPoll;
+
+        pub type Wait = TaskPoll;
+
+        pub type Kill = ();
+
+        pub type Release = ();
+    }
+}
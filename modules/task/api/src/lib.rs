@@ -1,3 +1,4 @@
 pub mod task_instance;
+pub mod task_log;
 pub mod task_manager;
 pub mod task_state;
@@ -5,14 +5,13 @@ use std::{
     task::{Context, Poll},
 };
 
-use ipis::{
-    core::{
-        anyhow::{bail, Result},
-        value::text::Text,
-    },
-    tokio::{self, sync::Mutex},
+use ipis::tokio::{
+    self,
+    sync::{watch, Mutex, Notify},
 };
 
+use ipwis_modules_task_common::task_error::TaskError;
+
 use crate::{task_manager::TaskManager, task_state::TaskState};
 
 pub struct TaskInstance<R, T>
@@ -20,22 +19,48 @@ where
     T: TaskManager,
 {
     pub state: Arc<Mutex<TaskState<T>>>,
-    pub handler: tokio::task::JoinHandle<Result<R, Text>>,
+    pub handler: tokio::task::JoinHandle<Result<R, TaskError>>,
+    pub kill_switch: Arc<Notify>,
+    pub finished: watch::Receiver<bool>,
+}
+
+impl<R, T> TaskInstance<R, T>
+where
+    T: TaskManager,
+{
+    pub fn kill(&self) {
+        // note: the permit is stored even if the task is not listening yet
+        self.kill_switch.notify_one()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        *self.finished.borrow() || self.handler.is_finished()
+    }
+
+    pub async fn wait_finished(mut finished: watch::Receiver<bool>) {
+        loop {
+            let is_finished = *finished.borrow();
+            // note: a closed channel means that the task has been finished or panicked
+            if is_finished || finished.changed().await.is_err() {
+                break;
+            }
+        }
+    }
 }
 
 impl<R, T> Future for TaskInstance<R, T>
 where
     T: TaskManager,
 {
-    type Output = Result<R>;
+    type Output = Result<R, TaskError>;
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         Pin::new(&mut self.handler)
             .poll(cx)
             .map(|result| match result {
-                Ok(Ok(outputs)) => Ok(outputs),
-                Ok(Err(errors)) => bail!("{}", errors.msg),
-                Err(error) => Err(error.into()),
+                Ok(outputs) => outputs,
+                // note: the task has been panicked or aborted
+                Err(error) => Err(TaskError::kernel(error)),
             })
     }
 }
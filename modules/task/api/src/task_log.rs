@@ -0,0 +1,61 @@
+use std::{collections::VecDeque, sync::Mutex};
+
+use ipwis_modules_task_common::task_logs::TaskLogs;
+
+// note: the default logs have no capacity, e.g. for the task graphs
+#[derive(Debug, Default)]
+pub struct TaskLog {
+    pub stdout: Mutex<TaskLogBuffer>,
+    pub stderr: Mutex<TaskLogBuffer>,
+}
+
+impl TaskLog {
+    pub fn snapshot(&self) -> TaskLogs {
+        let stdout = self.stdout.lock().unwrap();
+        let stderr = self.stderr.lock().unwrap();
+
+        TaskLogs {
+            stdout: stdout.data.iter().copied().collect(),
+            stderr: stderr.data.iter().copied().collect(),
+            dropped: stdout.dropped + stderr.dropped,
+            children: Default::default(),
+        }
+    }
+
+    pub fn stderr_tail(&self, len: usize) -> String {
+        let stderr = self.stderr.lock().unwrap();
+
+        let skip = stderr.data.len().saturating_sub(len);
+        let tail: Vec<_> = stderr.data.iter().skip(skip).copied().collect();
+        String::from_utf8_lossy(&tail).into_owned()
+    }
+}
+
+// a bounded ring buffer, which drops the oldest bytes
+#[derive(Debug, Default)]
+pub struct TaskLogBuffer {
+    capacity: usize,
+    data: VecDeque<u8>,
+    dropped: u64,
+}
+
+impl TaskLogBuffer {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            data: Default::default(),
+            dropped: 0,
+        }
+    }
+
+    pub fn write(&mut self, buf: &[u8]) {
+        self.data.extend(buf);
+
+        // evict the oldest bytes
+        let overflow = self.data.len().saturating_sub(self.capacity);
+        if overflow > 0 {
+            self.data.drain(..overflow);
+            self.dropped += overflow as u64;
+        }
+    }
+}
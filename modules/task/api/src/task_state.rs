@@ -1,9 +1,15 @@
-use std::sync::Arc;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+};
 
-use ipis::core::{account::GuarantorSigned, data::Data, value::chrono::DateTime};
-use ipwis_modules_task_common::task::Task;
+use ipis::{
+    core::{account::GuarantorSigned, data::Data, value::chrono::DateTime},
+    tokio::sync::Mutex,
+};
+use ipwis_modules_task_common::{task::Task, task_status::TaskStatus};
 
-use crate::task_manager::TaskManager;
+use crate::{task_log::TaskLog, task_manager::TaskManager};
 
 #[derive(Clone, Debug)]
 pub struct TaskState<T>
@@ -13,4 +19,12 @@ where
     pub manager: Arc<T>,
     pub task: Data<GuarantorSigned, Task>,
     pub created_date: DateTime,
+    pub started_date: Option<DateTime>,
+    pub finished_date: Option<DateTime>,
+    pub fuel_consumed: Option<u64>,
+    pub resources: BTreeSet<String>,
+    pub children: BTreeMap<String, TaskStatus>,
+    // note: the states of the task graphs' and pipelines' members
+    pub members: BTreeMap<String, Arc<Mutex<TaskState<T>>>>,
+    pub logs: Arc<TaskLog>,
 }
@@ -4,9 +4,13 @@ use std::{
 };
 
 use ipis::{
-    async_trait::async_trait, core::anyhow::Result, resource::Resource, rkyv::AlignedVec,
+    async_trait::async_trait,
+    core::anyhow::{bail, Result},
+    resource::Resource,
+    rkyv::AlignedVec,
     tokio::sync::Mutex,
 };
+use ipwis_modules_task_api::task_state::TaskState;
 use ipwis_modules_task_common_wasi::interrupt_id::InterruptId;
 
 use crate::{
@@ -17,24 +21,57 @@ pub(crate) type IpwisInterruptHandler = Arc<Mutex<Box<dyn InterruptHandler>>>;
 
 pub struct InterruptHandlerState {
     manager: Arc<IpwisTaskManager>,
+    state: Arc<Mutex<TaskState<IpwisTaskManager>>>,
     map: HashMap<InterruptId, IpwisInterruptHandler>,
+    pub last_syscall: Option<String>,
 }
 
 impl InterruptHandlerState {
-    pub(crate) fn with_manager(manager: Arc<IpwisTaskManager>) -> Self {
+    pub(crate) fn new(
+        manager: Arc<IpwisTaskManager>,
+        state: Arc<Mutex<TaskState<IpwisTaskManager>>>,
+    ) -> Self {
         Self {
             manager,
+            state,
             map: Default::default(),
+            last_syscall: None,
         }
     }
+
+    async fn load(&mut self, handler: InterruptId) -> Result<()> {
+        if let Entry::Vacant(e) = self.map.entry(handler) {
+            let mut state = self.state.lock().await;
+
+            // check the capability
+            if !state
+                .task
+                .constraints
+                .capabilities
+                .iter()
+                .any(|capability| capability == handler.0)
+            {
+                bail!("capability not granted: {}", handler.0);
+            }
+
+            e.insert(
+                self.manager
+                    .interrupt_manager
+                    .get(&handler, &self.state)
+                    .await?,
+            );
+
+            // mark as a held resource
+            state.resources.insert(handler.0.to_string());
+        }
+        Ok(())
+    }
 }
 
 impl InterruptHandlerState {
     pub async fn get(&mut self, handler: InterruptId) -> Result<IpwisInterruptHandler> {
         // load interrupt module
-        if let Entry::Vacant(e) = self.map.entry(handler) {
-            e.insert(self.manager.interrupt_manager.get(&handler).await?);
-        }
+        self.load(handler).await?;
         Ok(self.map.get_mut(&handler).unwrap().clone())
     }
 
@@ -44,10 +81,13 @@ impl InterruptHandlerState {
         handler: InterruptId,
         inputs: &[u8],
     ) -> Result<AlignedVec> {
-        // load interrupt module
-        if let Entry::Vacant(e) = self.map.entry(handler) {
-            e.insert(self.manager.interrupt_manager.get(&handler).await?);
+        // record the last syscall for the error reports
+        if self.last_syscall.as_deref() != Some(handler.0) {
+            self.last_syscall = Some(handler.0.to_string());
         }
+
+        // load interrupt module
+        self.load(handler).await?;
         let handler = self.map.get(&handler).unwrap();
 
         handler.lock().await.handle_raw(memory, inputs).await
@@ -7,9 +7,13 @@ use ipis::{
     core::anyhow::{anyhow, bail, Result},
     tokio::sync::Mutex,
 };
+use ipwis_modules_task_api::task_state::TaskState;
 use ipwis_modules_task_common_wasi::interrupt_id::InterruptId;
 
-use crate::{interrupt_handler_state::IpwisInterruptHandler, interrupt_module::InterruptModule};
+use crate::{
+    interrupt_handler_state::IpwisInterruptHandler, interrupt_module::InterruptModule,
+    task_manager::IpwisTaskManager,
+};
 
 type IpwisInterruptModule = Box<dyn InterruptModule>;
 
@@ -19,13 +23,21 @@ pub struct InterruptManager {
 }
 
 impl InterruptManager {
-    pub async fn get(&self, id: &InterruptId) -> Result<IpwisInterruptHandler> {
+    pub async fn get(
+        &self,
+        id: &InterruptId,
+        state: &Arc<Mutex<TaskState<IpwisTaskManager>>>,
+    ) -> Result<IpwisInterruptHandler> {
         let map = self.map.lock().await;
         let module = map
             .get(id)
             .ok_or_else(|| anyhow!("failed to find the interrupt module: {id}"))?;
 
-        module.spawn_handler().await.map(Mutex::new).map(Arc::new)
+        module
+            .spawn_handler_for(state)
+            .await
+            .map(Mutex::new)
+            .map(Arc::new)
     }
 
     pub async fn put<T>(&self, module: T) -> Result<()>
@@ -1,9 +1,13 @@
-use ipis::{async_trait::async_trait, core::anyhow::Result};
+use std::sync::Arc;
+
+use ipis::{async_trait::async_trait, core::anyhow::Result, tokio::sync::Mutex};
+use ipwis_modules_task_api::task_state::TaskState;
 use ipwis_modules_task_common_wasi::interrupt_id::InterruptId;
 
 use crate::{
     interrupt_handler::InterruptHandler,
     memory::{IpwisMemory, Memory},
+    task_manager::IpwisTaskManager,
 };
 
 #[async_trait]
@@ -15,4 +19,13 @@ where
     fn id(&self) -> InterruptId;
 
     async fn spawn_handler(&self) -> Result<Box<dyn InterruptHandler<M>>>;
+
+    // note: the modules which depend on the caller task can override it
+    async fn spawn_handler_for(
+        &self,
+        state: &Arc<Mutex<TaskState<IpwisTaskManager>>>,
+    ) -> Result<Box<dyn InterruptHandler<M>>> {
+        let _ = state;
+        self.spawn_handler().await
+    }
 }
@@ -6,5 +6,10 @@ mod interrupt_manager;
 pub mod interrupt_module;
 mod intrinsics;
 pub mod memory;
+pub mod module_cache;
+pub mod module_validator;
+pub mod program_cache;
 mod task_ctx;
+mod task_limits;
+mod task_log;
 pub mod task_manager;
@@ -0,0 +1,156 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use ipis::{
+    core::{anyhow::Result, value::hash::Hash},
+    env::infer,
+    log::{info, warn},
+    tokio::{self, sync::Mutex},
+};
+use ipwis_modules_task_common_wasi::program::Program;
+use wasmtime::{Engine, Module};
+
+/// The default number of the compiled modules kept in memory.
+const DEFAULT_CAPACITY: usize = 64;
+
+pub struct ModuleCache {
+    engine: Engine,
+    dir: Option<PathBuf>,
+    entries: Mutex<ModuleCacheEntries>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+struct ModuleCacheEntries {
+    capacity: usize,
+    map: HashMap<Hash, Module>,
+    order: VecDeque<Hash>,
+}
+
+impl ModuleCacheEntries {
+    fn get(&mut self, hash: &Hash) -> Option<Module> {
+        let module = self.map.get(hash)?.clone();
+
+        // mark as the most recently used
+        self.order.retain(|e| e != hash);
+        self.order.push_back(*hash);
+        Some(module)
+    }
+
+    fn put(&mut self, hash: Hash, module: Module) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        if self.map.insert(hash, module).is_some() {
+            self.order.retain(|e| e != &hash);
+        }
+        self.order.push_back(hash);
+
+        // evict the least recently used
+        while self.order.len() > self.capacity {
+            if let Some(hash) = self.order.pop_front() {
+                self.map.remove(&hash);
+            }
+        }
+    }
+}
+
+impl ModuleCache {
+    pub fn new(engine: Engine) -> Self {
+        Self {
+            engine,
+            dir: infer("ipwis_module_cache_dir").ok(),
+            entries: Mutex::new(ModuleCacheEntries {
+                capacity: infer("ipwis_module_cache_capacity").unwrap_or(DEFAULT_CAPACITY),
+                map: Default::default(),
+                order: Default::default(),
+            }),
+            hits: Default::default(),
+            misses: Default::default(),
+        }
+    }
+
+    pub fn hits(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    pub fn misses(&self) -> u64 {
+        self.misses.load(Ordering::Relaxed)
+    }
+
+    pub async fn get_or_compile(&self, program: &Program) -> Result<Module> {
+        let hash = Hash::with_bytes(program);
+
+        // find the module in memory
+        if let Some(module) = self.entries.lock().await.get(&hash) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(module);
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+
+        // note: the compilation is done on the blocking pool not to stall the runtime
+        let module = {
+            let engine = self.engine.clone();
+            let path = self
+                .dir
+                .as_ref()
+                .map(|dir| dir.join(format!("{hash}.cwasm")));
+            let program = program.to_vec();
+            tokio::task::spawn_blocking(move || load_or_compile(&engine, path, &program)).await??
+        };
+
+        self.entries.lock().await.put(hash, module.clone());
+        Ok(module)
+    }
+
+    pub async unsafe fn get_or_deserialize(&self, hash: Hash, artifact: &[u8]) -> Result<Module> {
+        // find the module in memory
+        if let Some(module) = self.entries.lock().await.get(&hash) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(module);
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+
+        // note: the engine rejects the artifacts made by the incompatible configs
+        let module = Module::deserialize(&self.engine, artifact)?;
+
+        self.entries.lock().await.put(hash, module.clone());
+        Ok(module)
+    }
+}
+
+fn load_or_compile(engine: &Engine, path: Option<PathBuf>, program: &[u8]) -> Result<Module> {
+    // find the module on disk
+    if let Some(path) = path.as_ref().filter(|path| path.exists()) {
+        // safety: the file is serialized by this cache with the same engine
+        match unsafe { Module::deserialize_file(engine, path) } {
+            Ok(module) => return Ok(module),
+            Err(error) => warn!("failed to load the cached module: {error}"),
+        }
+    }
+
+    info!("compiling a module");
+    let module = Module::from_binary(engine, program)?;
+
+    // store the module on disk
+    if let Some(path) = path {
+        if let Err(error) = module.serialize().and_then(|bytes| store(&path, &bytes)) {
+            warn!("failed to store the cached module: {error}");
+        }
+    }
+    Ok(module)
+}
+
+fn store(path: &Path, bytes: &[u8]) -> Result<()> {
+    // note: the file is replaced at once, as it may be mapped by the other tasks
+    let dir = path.parent().unwrap_or_else(|| Path::new("."));
+    let mut file = ::tempfile::NamedTempFile::new_in(dir)?;
+    file.write_all(bytes)?;
+    file.persist(path)?;
+    Ok(())
+}
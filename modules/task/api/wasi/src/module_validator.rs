@@ -0,0 +1,92 @@
+use ipwis_modules_task_common_wasi::extrinsics::{memory, syscall};
+use wasmtime::{ExternType, FuncType, Linker, Module, Store, ValType};
+
+pub use ipwis_modules_task_common::task_error::ModuleRejection;
+
+pub fn validate_exports(module: &Module) -> Result<(), ModuleRejection> {
+    // check the linear memory
+    match module.get_export(memory::MEMORY) {
+        Some(ExternType::Memory(_)) => {}
+        Some(_) => {
+            return Err(ModuleRejection::MismatchedExport {
+                name: memory::MEMORY.to_string(),
+            })
+        }
+        None => {
+            return Err(ModuleRejection::MissingExport {
+                name: memory::MEMORY.to_string(),
+            })
+        }
+    }
+
+    // check the functions
+    let functions = [
+        (memory::IPWIS_ALLOC, func_type(2, true)),
+        (memory::IPWIS_ALLOC_ZEROED, func_type(2, true)),
+        (memory::IPWIS_DEALLOC, func_type(3, false)),
+        (memory::IPWIS_REALLOC, func_type(4, true)),
+        (syscall::SYSCALL, syscall_type()),
+    ];
+    for (name, ty) in functions {
+        match module.get_export(name) {
+            Some(ExternType::Func(func)) if func == ty => {}
+            Some(_) => {
+                return Err(ModuleRejection::MismatchedExport {
+                    name: name.to_string(),
+                })
+            }
+            None => {
+                return Err(ModuleRejection::MissingExport {
+                    name: name.to_string(),
+                })
+            }
+        }
+    }
+    Ok(())
+}
+
+// note: the imports are resolved by the linker, which defines the allowed WASI surface
+pub fn validate_imports<T>(
+    module: &Module,
+    linker: &Linker<T>,
+    store: &mut Store<T>,
+) -> Result<(), ModuleRejection> {
+    for import in module.imports() {
+        let module = import.module();
+        let name = import.name();
+
+        let defined = match linker.get(&mut *store, module, name) {
+            Some(defined) => defined,
+            None => {
+                return Err(ModuleRejection::UnknownImport {
+                    module: module.to_string(),
+                    name: name.to_string(),
+                })
+            }
+        };
+
+        // check the function types
+        match (import.ty(), defined.ty(&*store)) {
+            (ExternType::Func(expected), ExternType::Func(found)) if expected == found => {}
+            _ => {
+                return Err(ModuleRejection::MismatchedImport {
+                    module: module.to_string(),
+                    name: name.to_string(),
+                })
+            }
+        }
+    }
+    Ok(())
+}
+
+fn syscall_type() -> FuncType {
+    func_type(4, true)
+}
+
+fn func_type(num_params: usize, has_result: bool) -> FuncType {
+    // note: `ExternDataRef` is a 32-bit integer on the guest side
+    FuncType::new(
+        ::std::iter::repeat(ValType::I32).take(num_params),
+        has_result.then_some(ValType::I32),
+    )
+}
@@ -0,0 +1,94 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Arc,
+};
+
+use ipis::{
+    core::{
+        anyhow::{bail, Result},
+        signed::IsSigned,
+        value::hash::Hash,
+    },
+    env::infer,
+    path::Path,
+    tokio::sync::Mutex,
+};
+
+/// The default total size of the programs kept in memory, in bytes.
+const DEFAULT_CAPACITY: u64 = 256 * 1024 * 1024;
+
+pub struct ProgramCache {
+    entries: Mutex<ProgramCacheEntries>,
+}
+
+struct ProgramCacheEntries {
+    capacity: u64,
+    size: u64,
+    map: HashMap<Hash, Arc<Vec<u8>>>,
+    order: VecDeque<Hash>,
+}
+
+impl Default for ProgramCache {
+    fn default() -> Self {
+        Self {
+            entries: Mutex::new(ProgramCacheEntries {
+                capacity: infer("ipwis_program_cache_capacity").unwrap_or(DEFAULT_CAPACITY),
+                size: 0,
+                map: Default::default(),
+                order: Default::default(),
+            }),
+        }
+    }
+}
+
+impl ProgramCache {
+    pub async fn get(&self, path: &Path) -> Option<Arc<Vec<u8>>> {
+        let mut entries = self.entries.lock().await;
+        let program = entries.map.get(&path.value)?.clone();
+
+        // mark as the most recently used
+        entries.order.retain(|e| e != &path.value);
+        entries.order.push_back(path.value);
+        Some(program)
+    }
+
+    pub async fn put(&self, path: &Path, program: Vec<u8>) -> Result<Arc<Vec<u8>>> {
+        verify(path, &program)?;
+        let program = Arc::new(program);
+
+        let mut entries = self.entries.lock().await;
+        let len = program.len() as u64;
+        if len > entries.capacity || entries.map.contains_key(&path.value) {
+            return Ok(program);
+        }
+
+        // evict the least recently used
+        while entries.size + len > entries.capacity {
+            match entries.order.pop_front() {
+                Some(hash) => {
+                    if let Some(evicted) = entries.map.remove(&hash) {
+                        entries.size -= evicted.len() as u64;
+                    }
+                }
+                None => break,
+            }
+        }
+
+        entries.size += len;
+        entries.map.insert(path.value, program.clone());
+        entries.order.push_back(path.value);
+        Ok(program)
+    }
+}
+
+pub fn verify<T>(path: &Path, data: &T) -> Result<()>
+where
+    T: IsSigned,
+{
+    // note: the path is computed over the stored payload, which is the serialized data
+    let payload = data.to_bytes()?;
+    if payload.len() as u64 != path.len || Hash::with_bytes(&payload) != path.value {
+        bail!("corrupted data: {}", path.value);
+    }
+    Ok(())
+}
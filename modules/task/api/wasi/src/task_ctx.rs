@@ -1,42 +1,108 @@
 use std::sync::Arc;
 
 use ipis::{
-    async_trait::async_trait, core::anyhow::Result, resource::Resource, tokio::sync::Mutex,
+    async_trait::async_trait,
+    core::anyhow::Result,
+    env::infer,
+    log::warn,
+    resource::Resource,
+    tokio::{self, sync::Mutex},
 };
-use ipwis_modules_task_api::task_state::TaskState;
-use wasmtime_wasi::{WasiCtx, WasiCtxBuilder};
+use ipwis_modules_task_api::{task_log::TaskLog, task_state::TaskState};
+use ipwis_modules_task_common::task_wasi::TaskWasi;
+use tempfile::TempDir;
+use wasi_common::pipe::WritePipe;
+use wasmtime_wasi::{ambient_authority, Dir, WasiCtx, WasiCtxBuilder};
 
-use crate::{interrupt_handler_state::InterruptHandlerState, task_manager::IpwisTaskManager};
+use crate::{
+    interrupt_handler_state::InterruptHandlerState, task_limits::IpwisTaskLimits,
+    task_log::TaskLogWriter, task_manager::IpwisTaskManager,
+};
 
 pub struct IpwisTaskCtx {
     pub wasi: WasiCtx,
     pub state: Arc<Mutex<TaskState<IpwisTaskManager>>>,
     pub interrupt_handler_state: InterruptHandlerState,
+    pub limits: IpwisTaskLimits,
+    // note: the directories are removed on drop, even if the task is not run
+    scratch_dirs: Vec<TempDir>,
 }
 
 impl IpwisTaskCtx {
     pub fn try_new(
         manager: Arc<IpwisTaskManager>,
         state: Arc<Mutex<TaskState<IpwisTaskManager>>>,
+        limits: IpwisTaskLimits,
+        wasi: &TaskWasi,
+        logs: &Arc<TaskLog>,
     ) -> Result<Self> {
+        // create a WASI context and put it in a Store; all instances in the store
+        // share this context. `WasiCtxBuilder` provides a number of ways to
+        // configure what the target program will have access to.
+        // note: nothing is inherited from the host by default
+        let mut builder = WasiCtxBuilder::new()
+            .args(&wasi.args)?
+            .envs(&wasi.envs)?
+            // capture the stdout/stderr
+            .stdout(Box::new(WritePipe::new(TaskLogWriter::stdout(
+                logs.clone(),
+            ))))
+            .stderr(Box::new(WritePipe::new(TaskLogWriter::stderr(
+                logs.clone(),
+            ))));
+
+        // map the scratch directories
+        let mut scratch_dirs = vec![];
+        for guest_path in &wasi.scratch_dirs {
+            let host_dir = new_scratch_dir()?;
+            let dir = Dir::open_ambient_dir(host_dir.path(), ambient_authority())?;
+            scratch_dirs.push(host_dir);
+
+            builder = builder.preopened_dir(dir, guest_path)?;
+        }
+
         Ok(Self {
-            // create a WASI context and put it in a Store; all instances in the store
-            // share this context. `WasiCtxBuilder` provides a number of ways to
-            // configure what the target program will have access to.
-            wasi: WasiCtxBuilder::new()
-                .inherit_stdio()
-                .inherit_args()?
-                .build(),
+            wasi: builder.build(),
+            interrupt_handler_state: InterruptHandlerState::new(manager, state.clone()),
             state,
-            interrupt_handler_state: InterruptHandlerState::with_manager(manager),
+            limits,
+            scratch_dirs,
         })
     }
 }
 
+fn new_scratch_dir() -> Result<TempDir> {
+    let parent = infer("ipwis_task_scratch_dir").unwrap_or_else(|_| ::std::env::temp_dir());
+    ::std::fs::create_dir_all(&parent)?;
+
+    // note: the directory is created exclusively, with an unpredictable name
+    ::tempfile::Builder::new()
+        .prefix("ipwis-task-")
+        .tempdir_in(parent)
+        .map_err(Into::into)
+}
+
 #[async_trait]
 impl Resource for IpwisTaskCtx {
     async fn release(&mut self) -> Result<()> {
-        self.interrupt_handler_state.release().await?;
-        Ok(())
+        // note: the other resources are released even if the handlers are failed
+        let result = self.interrupt_handler_state.release().await;
+        self.state.lock().await.resources.clear();
+
+        // remove the scratch directories
+        let scratch_dirs: Vec<_> = self.scratch_dirs.drain(..).collect();
+        for result in tokio::task::spawn_blocking(move || {
+            scratch_dirs
+                .into_iter()
+                .map(TempDir::close)
+                .collect::<Vec<_>>()
+        })
+        .await?
+        {
+            if let Err(error) = result {
+                warn!("failed to remove the scratch directory: {error}");
+            }
+        }
+        result
     }
 }
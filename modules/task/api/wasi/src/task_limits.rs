@@ -0,0 +1,58 @@
+use ipis::core::anyhow::Result;
+use ipwis_modules_task_common::task_resource_constraints::TaskResourceConstraints;
+use wasmtime::{ResourceLimiter, StoreLimits, StoreLimitsBuilder};
+
+pub struct IpwisTaskLimits {
+    inner: StoreLimits,
+    memory_exceeded: bool,
+}
+
+impl IpwisTaskLimits {
+    pub fn try_new(resources: &TaskResourceConstraints) -> Result<Self> {
+        let mut builder = StoreLimitsBuilder::new();
+        if let Some(max_memory) = resources.max_memory {
+            builder = builder.memory_size(max_memory.try_into()?);
+        }
+        if let Some(max_table_elements) = resources.max_table_elements {
+            builder = builder.table_elements(max_table_elements);
+        }
+        if let Some(max_instances) = resources.max_instances {
+            builder = builder.instances(max_instances.try_into()?);
+        }
+
+        Ok(Self {
+            inner: builder.build(),
+            memory_exceeded: false,
+        })
+    }
+
+    pub fn is_memory_exceeded(&self) -> bool {
+        self.memory_exceeded
+    }
+}
+
+impl ResourceLimiter for IpwisTaskLimits {
+    fn memory_growing(&mut self, current: usize, desired: usize, maximum: Option<usize>) -> bool {
+        let growable = self.inner.memory_growing(current, desired, maximum);
+        if !growable {
+            self.memory_exceeded = true;
+        }
+        growable
+    }
+
+    fn table_growing(&mut self, current: u32, desired: u32, maximum: Option<u32>) -> bool {
+        self.inner.table_growing(current, desired, maximum)
+    }
+
+    fn instances(&self) -> usize {
+        self.inner.instances()
+    }
+
+    fn tables(&self) -> usize {
+        self.inner.tables()
+    }
+
+    fn memories(&self) -> usize {
+        self.inner.memories()
+    }
+}
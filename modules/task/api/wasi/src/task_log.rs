@@ -0,0 +1,70 @@
+use std::{io::Write, sync::Arc};
+
+use ipis::env::infer;
+use ipwis_modules_task_api::task_log::{TaskLog, TaskLogBuffer};
+
+/// The default capacity of each stdout/stderr buffer of a task.
+const DEFAULT_CAPACITY: usize = 64 * 1024;
+
+pub struct TaskLogConfig {
+    capacity: usize,
+}
+
+impl Default for TaskLogConfig {
+    fn default() -> Self {
+        Self {
+            capacity: infer("ipwis_task_log_capacity").unwrap_or(DEFAULT_CAPACITY),
+        }
+    }
+}
+
+impl TaskLogConfig {
+    pub fn create(&self) -> Arc<TaskLog> {
+        Arc::new(TaskLog {
+            stdout: TaskLogBuffer::new(self.capacity).into(),
+            stderr: TaskLogBuffer::new(self.capacity).into(),
+        })
+    }
+}
+
+#[derive(Copy, Clone)]
+enum TaskLogStream {
+    Stdout,
+    Stderr,
+}
+
+pub struct TaskLogWriter {
+    log: Arc<TaskLog>,
+    stream: TaskLogStream,
+}
+
+impl TaskLogWriter {
+    pub fn stdout(log: Arc<TaskLog>) -> Self {
+        Self {
+            log,
+            stream: TaskLogStream::Stdout,
+        }
+    }
+
+    pub fn stderr(log: Arc<TaskLog>) -> Self {
+        Self {
+            log,
+            stream: TaskLogStream::Stderr,
+        }
+    }
+}
+
+impl Write for TaskLogWriter {
+    fn write(&mut self, buf: &[u8]) -> ::std::io::Result<usize> {
+        let buffer = match self.stream {
+            TaskLogStream::Stdout => &self.log.stdout,
+            TaskLogStream::Stderr => &self.log.stderr,
+        };
+        buffer.lock().unwrap().write(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> ::std::io::Result<()> {
+        Ok(())
+    }
+}
@@ -1,4 +1,4 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 use ipis::{
     async_trait::async_trait,
@@ -6,33 +6,55 @@ use ipis::{
         account::GuarantorSigned,
         anyhow::{bail, Result},
         data::Data,
-        value::{chrono::DateTime, text::Text},
+        value::chrono::DateTime,
     },
+    log::{info, warn},
     object::data::ObjectData,
+    path::Path,
     pin::PinnedInner,
     resource::Resource,
-    tokio::{self, sync::Mutex},
+    tokio::{
+        self,
+        sync::{watch, Mutex, Notify},
+    },
 };
 use ipwis_modules_task_api::{
-    task_instance::TaskInstance, task_manager::TaskManager, task_state::TaskState,
+    task_instance::TaskInstance, task_log::TaskLog, task_manager::TaskManager,
+    task_state::TaskState,
+};
+use ipwis_modules_task_common::{
+    task::Task,
+    task_error::{TaskError, TaskErrorFrame, TaskErrorSymbol},
 };
-use ipwis_modules_task_common::task::Task;
 use ipwis_modules_task_common_wasi::{
     extern_data::{ExternData, ExternDataRef},
     program::Program,
 };
-use wasmtime::{Config, Engine, Linker, Module, Store, Trap};
+use wasmtime::{Config, Engine, Linker, Module, Store, Trap, WasmBacktraceDetails};
 
 use crate::{
     interrupt_manager::InterruptManager,
     intrinsics::syscall,
     memory::{IpwisMemoryInner, Memory},
+    module_cache::ModuleCache,
+    module_validator,
     task_ctx::IpwisTaskCtx,
+    task_limits::IpwisTaskLimits,
+    task_log::TaskLogConfig,
 };
 
+/// The interval of the engine's epoch, which is the time slice of each task.
+const EPOCH_INTERVAL: Duration = Duration::from_millis(10);
+
+/// The maximum length of the stderr attached to the error reports.
+const TRAP_STDERR_LEN: usize = 4 * 1024;
+
 pub struct IpwisTaskManager {
     linker: Linker<IpwisTaskCtx>,
     pub interrupt_manager: Arc<InterruptManager>,
+    pub module_cache: ModuleCache,
+    log_config: TaskLogConfig,
+    _epoch_ticker: Arc<()>,
 }
 
 #[async_trait]
@@ -45,107 +67,353 @@ impl TaskManager for IpwisTaskManager {
         task: Data<GuarantorSigned, Task>,
         program: &<Self as TaskManager>::Program,
     ) -> Result<TaskInstance<Box<ObjectData>, Self>> {
+        let module = self.module_cache.get_or_compile(program).await?;
+        self.spawn_module(task, module).await
+    }
+}
+
+async fn wait_until(due_date: DateTime) {
+    if due_date == DateTime::MAX_DATETIME {
+        return ::core::future::pending().await;
+    }
+
+    // note: the due date is re-checked after sleeping, as the wall clock may be skewed
+    while let Some(remaining) = remaining_until(&due_date) {
+        tokio::time::sleep(remaining).await;
+    }
+}
+
+fn remaining_until(due_date: &DateTime) -> Option<Duration> {
+    // note: a passed due date is negative, which is rejected by `to_std`
+    (**due_date - *DateTime::now())
+        .to_std()
+        .ok()
+        .filter(|remaining| !remaining.is_zero())
+}
+
+impl IpwisTaskManager {
+    pub async fn try_new() -> Result<Self> {
+        // define the WASI functions globally on the `Config`.
+        let engine = Engine::new(
+            Config::new()
+                .async_support(true)
+                .consume_fuel(true)
+                .epoch_interruption(true)
+                // resolve the symbols of the backtraces from DWARF
+                .wasm_backtrace_details(WasmBacktraceDetails::Enable),
+        )?;
+
+        // create a linker
+        let mut linker = Linker::new(&engine);
+        ::wasmtime_wasi::add_to_linker(&mut linker, |ctx: &mut IpwisTaskCtx| &mut ctx.wasi)?;
+
+        // register intrinsics
+        {
+            crate::intrinsics::syscall::linker::__syscall(&mut linker)?;
+        }
+
+        // create an interrupt maanger
+        let interrupt_manager = Default::default();
+
+        // create a module cache
+        let module_cache = ModuleCache::new(engine.clone());
+
+        // tick the epoch until the manager is dropped
+        // note: a dedicated thread is used so that busy tasks cannot starve it
+        let epoch_ticker = Arc::new(());
+        {
+            let ticker = Arc::downgrade(&epoch_ticker);
+            ::std::thread::spawn(move || {
+                while ticker.strong_count() > 0 {
+                    ::std::thread::sleep(EPOCH_INTERVAL);
+                    engine.increment_epoch();
+                }
+            });
+        }
+
+        Ok(Self {
+            linker,
+            interrupt_manager,
+            module_cache,
+            log_config: Default::default(),
+            _epoch_ticker: epoch_ticker,
+        })
+    }
+
+    pub async fn precompile(&self, program: &Program) -> Result<Vec<u8>> {
+        // note: the compilation is done on the blocking pool not to stall the runtime
+        let engine = self.linker.engine().clone();
+        let program = program.to_vec();
+        tokio::task::spawn_blocking(move || engine.precompile_module(&program)).await?
+    }
+
+    pub async unsafe fn spawn_precompiled(
+        self: &Arc<Self>,
+        task: Data<GuarantorSigned, Task>,
+        source: &Path,
+        artifact: &[u8],
+    ) -> Result<TaskInstance<Box<ObjectData>, Self>> {
+        // note: the artifact should be made by `Self::precompile` on a trusted node,
+        //       as it is loaded as a native code
+        let module = self
+            .module_cache
+            .get_or_deserialize(source.value, artifact)
+            .await?;
+        self.spawn_module(task, module).await
+    }
+
+    async fn spawn_module(
+        self: &Arc<Self>,
+        task: Data<GuarantorSigned, Task>,
+        module: Module,
+    ) -> Result<TaskInstance<Box<ObjectData>, Self>> {
+        // validate the program before allocating any resources
+        // note: the rejection is structured, so that the clients can inspect it
+        module_validator::validate_exports(&module)
+            .map_err(|reason| TaskError::Rejected { reason })?;
+
+        // validate the inputs
+        task.constraints.check_inputs()?;
+
+        // reject the task if it is already expired
+        let due_date = task.constraints.resources.due_date.clone();
+        if due_date <= DateTime::now() {
+            bail!("task is already expired");
+        }
+        let fuel = task.constraints.resources.fuel;
+        let limits = IpwisTaskLimits::try_new(&task.constraints.resources)?;
+        let wasi = task.constraints.wasi.clone();
+        let logs = self.log_config.create();
+
         // create a new state
         let state = Arc::new(Mutex::new(TaskState {
             manager: self.clone(),
             task,
             created_date: DateTime::now(),
+            started_date: None,
+            finished_date: None,
+            fuel_consumed: None,
+            resources: Default::default(),
+            children: Default::default(),
+            members: Default::default(),
+            logs: logs.clone(),
         }));
 
         // create a new store
         let mut store = Store::new(
             self.linker.engine(),
-            IpwisTaskCtx::try_new(self.clone(), state.clone())?,
+            IpwisTaskCtx::try_new(self.clone(), state.clone(), limits, &wasi, &logs)?,
         );
 
-        // create an instance with given module and store
-        let module = Module::from_binary(self.linker.engine(), program)?;
-        let instance = self.linker.instantiate_async(&mut store, &module).await?;
+        // limit the memory and tables
+        store.limiter(|ctx| &mut ctx.limits);
+
+        // yield to the scheduler on every epoch so that the task can be interrupted
+        // note: this also time-slices busy tasks fairly with the other futures on the runtime
+        store.epoch_deadline_async_yield_and_update(1);
+
+        // prepare the task
+        // note: the held resources are released if failed, as the task is not run
+        let prepared = (|| {
+            // check the imports against the linker
+            module_validator::validate_imports(&module, &self.linker, &mut store)
+                .map_err(|reason| TaskError::Rejected { reason })?;
 
-        // find main function
-        let func = syscall::instance::__syscall(&instance, &mut store)?;
+            // limit the computation with the given fuel
+            // note: the unlimited fuel is saturated by `wasmtime`
+            store.add_fuel(fuel.unwrap_or(u64::MAX))?;
+            store.out_of_fuel_trap();
+
+            // resolve the imports of the module
+            // note: the start function is run with the task, bounded by the due date
+            self.linker.instantiate_pre(&mut store, &module)
+        })();
+        let instance_pre = match prepared {
+            Ok(prepared) => prepared,
+            Err(error) => {
+                if let Err(error) = store.data_mut().release().await {
+                    warn!("failed to release the task: {error}");
+                }
+                return Err(error);
+            }
+        };
 
         // external call
         // note: the inner schedule is controlled by `wasmtime` engine, not by this scheduler
+        let kill_switch: Arc<Notify> = Default::default();
+        let (finished_tx, finished) = watch::channel(false);
         let handler = {
-            let (inputs, outputs, errors) = {
-                let mut memory = IpwisMemoryInner::with_instance(&instance, &mut store)?;
-                let state = state.lock().await;
-
-                let inputs = memory
-                    .dump_doubled_object(&state.task.constraints.inputs)
-                    .await?;
-                let outputs = memory.dump_doubled_null().await?;
-                let errors = memory.dump_doubled_null().await?;
-                (inputs, outputs, errors)
-            };
-
+            let kill_switch = kill_switch.clone();
+            let state = state.clone();
             tokio::spawn(async move {
-                let result = func
-                    .call_async(
-                        &mut store,
-                        (0 /* nullptr */, inputs.ptr, outputs.ptr, errors.ptr),
-                    )
-                    .await;
+                state.lock().await.started_date = Some(DateTime::now());
+
+                // note: dropping the call cancels the execution on the next yield point
+                let result = tokio::select! {
+                    result = async {
+                        // create an instance with given module and store
+                        let instance = instance_pre.instantiate_async(&mut store).await?;
+
+                        // find main function
+                        let func = syscall::instance::__syscall(&instance, &mut store)?;
 
-                let memory = IpwisMemoryInner::with_instance(&instance, &mut store);
+                        // dump the inputs
+                        let mut memory = IpwisMemoryInner::with_instance(&instance, &mut store)?;
+                        let inputs = {
+                            let state = state.lock().await;
+                            memory
+                                .dump_doubled_object(&state.task.constraints.inputs)
+                                .await?
+                        };
+                        let outputs = memory.dump_doubled_null().await?;
+                        let errors = memory.dump_doubled_null().await?;
+
+                        let status = func
+                            .call_async(
+                                &mut store,
+                                (0 /* nullptr */, inputs.ptr, outputs.ptr, errors.ptr),
+                            )
+                            .await?;
+                        Ok::<_, ::ipis::core::anyhow::Error>((instance, outputs, errors, status))
+                    } => Ok(result),
+                    () = kill_switch.notified() => Err(TaskError::Cancelled),
+                    () = wait_until(due_date) => Err(TaskError::DeadlineExceeded),
+                };
 
                 fn parse_status_code<T>(
                     memory: Result<IpwisMemoryInner<&'_ mut Store<T>>>,
                     outputs: ExternData,
                     errors: ExternData,
-                    result: Result<ExternDataRef, Trap>,
-                ) -> Result<Box<ObjectData>>
+                    status: ExternDataRef,
+                    logs: &TaskLog,
+                ) -> Result<Box<ObjectData>, TaskError>
                 where
                     T: Resource + Send + Sync,
                 {
-                    let memory = memory?;
+                    let memory = memory.map_err(TaskError::kernel)?;
 
-                    match result {
-                        Ok(syscall::SYSCALL_OK) => {
+                    match status {
+                        syscall::SYSCALL_OK => {
                             // parse outputs as ObjectData
-                            let outputs = memory.load_doubled(outputs.ptr)?;
-                            PinnedInner::deserialize_owned(outputs).map(Box::new)
+                            let outputs = memory
+                                .load_doubled(outputs.ptr)
+                                .map_err(TaskError::kernel)?;
+                            PinnedInner::deserialize_owned(outputs)
+                                .map(Box::new)
+                                .map_err(TaskError::kernel)
                         }
-                        Ok(syscall::SYSCALL_ERR_NORMAL) => {
+                        syscall::SYSCALL_ERR_NORMAL => {
                             // parse errors as String
-                            let errors = memory.load_doubled(errors.ptr)?;
-                            bail!("{}", ::core::str::from_utf8(errors)?)
+                            let errors =
+                                memory.load_doubled(errors.ptr).map_err(TaskError::kernel)?;
+                            Err(TaskError::Guest {
+                                message: String::from_utf8_lossy(errors).into_owned(),
+                                stderr: logs.stderr_tail(TRAP_STDERR_LEN),
+                            })
                         }
-                        Ok(syscall::SYSCALL_ERR_FATAL) => bail!("fatal error"),
-                        Ok(_) => bail!("unknown status code"),
-                        Err(e) => bail!("trap: {e}"),
+                        syscall::SYSCALL_ERR_FATAL => Err(TaskError::SyscallFatal),
+                        _ => Err(TaskError::kernel("unknown status code")),
                     }
                 }
 
-                parse_status_code(memory, outputs, errors, result).map_err(Text::with_en_us)
-            })
-        };
+                fn parse_trap(
+                    trap: Trap,
+                    last_syscall: Option<String>,
+                    logs: &TaskLog,
+                ) -> TaskError {
+                    TaskError::Trap {
+                        code: trap.trap_code().map(|code| code.to_string()),
+                        message: trap.display_reason().to_string(),
+                        backtrace: trap
+                            .trace()
+                            .unwrap_or_default()
+                            .iter()
+                            .map(|frame| TaskErrorFrame {
+                                module: frame.module_name().map(ToString::to_string),
+                                func_index: frame.func_index(),
+                                func_name: frame.func_name().map(ToString::to_string),
+                                module_offset: frame.module_offset().map(|offset| offset as u64),
+                                symbols: frame
+                                    .symbols()
+                                    .iter()
+                                    .map(|symbol| TaskErrorSymbol {
+                                        name: symbol.name().map(ToString::to_string),
+                                        file: symbol.file().map(ToString::to_string),
+                                        line: symbol.line(),
+                                        column: symbol.column(),
+                                    })
+                                    .collect(),
+                            })
+                            .collect(),
+                        last_syscall,
+                        stderr: logs.stderr_tail(TRAP_STDERR_LEN),
+                    }
+                }
 
-        Ok(TaskInstance { state, handler })
-    }
-}
+                // report the consumed fuel
+                let fuel_consumed = store.fuel_consumed();
+                {
+                    let mut state = state.lock().await;
+                    state.finished_date = Some(DateTime::now());
+                    state.fuel_consumed = fuel_consumed;
+                }
+                if let Some(fuel_consumed) = fuel_consumed {
+                    info!("task finished: fuel consumed = {fuel_consumed}");
+                }
+                let out_of_fuel = matches!(
+                    (fuel, fuel_consumed),
+                    (Some(fuel), Some(fuel_consumed)) if fuel_consumed >= fuel
+                );
 
-impl IpwisTaskManager {
-    pub async fn try_new() -> Result<Self> {
-        // define the WASI functions globally on the `Config`.
-        let engine = Engine::new(Config::new().async_support(true))?;
+                let out_of_memory = store.data().limits.is_memory_exceeded();
 
-        // create a linker
-        let mut linker = Linker::new(&engine);
-        ::wasmtime_wasi::add_to_linker(&mut linker, |ctx: &mut IpwisTaskCtx| &mut ctx.wasi)?;
+                let outputs = match result {
+                    Ok(Err(_)) if out_of_fuel => Err(TaskError::OutOfFuel),
+                    Ok(Err(_)) if out_of_memory => Err(TaskError::OutOfMemory),
+                    Ok(Err(error)) => match error.downcast::<Trap>() {
+                        Ok(trap) => {
+                            let last_syscall =
+                                store.data().interrupt_handler_state.last_syscall.clone();
+                            Err(parse_trap(trap, last_syscall, &logs))
+                        }
+                        Err(error) => Err(TaskError::kernel(error)),
+                    },
+                    Ok(Ok((instance, outputs, errors, status))) => {
+                        let memory = IpwisMemoryInner::with_instance(&instance, &mut store);
+                        parse_status_code(memory, outputs, errors, status, &logs)
+                    }
+                    Err(error) => Err(error),
+                };
 
-        // register intrinsics
-        {
-            crate::intrinsics::syscall::linker::__syscall(&mut linker)?;
-        }
+                // validate the outputs
+                let outputs = match outputs {
+                    Ok(outputs) => {
+                        let state = state.lock().await;
+                        state
+                            .task
+                            .constraints
+                            .check_outputs(&outputs)
+                            .map(|()| outputs)
+                    }
+                    Err(error) => Err(error),
+                };
 
-        // create an interrupt maanger
-        let interrupt_manager = Default::default();
+                // release all resources the task has held
+                if let Err(error) = store.data_mut().release().await {
+                    warn!("failed to release the task: {error}");
+                }
 
-        Ok(Self {
-            linker,
-            interrupt_manager,
+                // notify the waiters
+                let _ = finished_tx.send(true);
+                outputs
+            })
+        };
+
+        Ok(TaskInstance {
+            state,
+            handler,
+            kill_switch,
+            finished,
         })
     }
 }
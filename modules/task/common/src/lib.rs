@@ -1,4 +1,12 @@
 pub mod task;
 pub mod task_constraints;
+pub mod task_error;
+pub mod task_exception;
+pub mod task_info;
+pub mod task_logs;
+pub mod task_pipeline;
 pub mod task_poll;
 pub mod task_resource_constraints;
+pub mod task_status;
+pub mod task_wait;
+pub mod task_wasi;
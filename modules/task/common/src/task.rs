@@ -17,6 +17,7 @@ use crate::task_constraints::TaskConstraints;
 pub struct Task {
     pub constraints: TaskConstraints,
     pub program: Option<Data<GuarantorSigned, Path>>,
+    pub artifact: Option<Data<GuarantorSigned, Path>>,
     #[omit_bounds]
     pub reserved: HashMap<String, Self>,
     #[omit_bounds]
@@ -30,6 +31,7 @@ impl Task {
         Self {
             constraints: TaskConstraints::new_sandbox(),
             program: None,
+            artifact: None,
             reserved: Default::default(),
             children: Default::default(),
             exceptions: Default::default(),
@@ -61,6 +63,12 @@ where
                 inner: ::bytecheck::ErrorBox::new(e),
             },
         )?;
+        CheckBytes::<__C>::check_bytes(::core::ptr::addr_of!((*value).artifact), context).map_err(
+            |e| ::bytecheck::StructCheckError {
+                field_name: stringify!(artifact),
+                inner: ::bytecheck::ErrorBox::new(e),
+            },
+        )?;
         CheckBytes::<__C>::check_bytes(::core::ptr::addr_of!((*value).reserved), context).map_err(
             |e| ::bytecheck::StructCheckError {
                 field_name: stringify!(reserved),
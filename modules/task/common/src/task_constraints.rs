@@ -2,29 +2,116 @@ use bytecheck::CheckBytes;
 use ipis::{
     class::{metadata::ClassMetadata, Class},
     core::signed::IsSigned,
-    object::{data::ObjectData, IntoObjectData},
+    object::{data::ObjectData, value::ObjectValue, IntoObjectData},
 };
 use rkyv::{Archive, Deserialize, Serialize};
 
-use crate::task_resource_constraints::TaskResourceConstraints;
+use crate::{
+    task_error::TaskError, task_resource_constraints::TaskResourceConstraints, task_wasi::TaskWasi,
+};
 
 #[derive(Clone, Debug, PartialEq, Archive, Serialize, Deserialize)]
 #[archive(compare(PartialEq))]
 #[archive_attr(derive(CheckBytes, Debug, PartialEq))]
 pub struct TaskConstraints {
     pub inputs: ObjectData,
+    // note: the inputs are not checked if not given
+    pub inputs_class: Option<ClassMetadata>,
     pub outputs: ClassMetadata,
     pub resources: TaskResourceConstraints,
+    pub capabilities: Vec<String>,
+    pub wasi: TaskWasi,
 }
 
 impl TaskConstraints {
     pub fn new_sandbox() -> Self {
         Self {
             inputs: ().__into_object_data(),
+            inputs_class: Some(<() as Class>::__class_metadata()),
             outputs: <() as Class>::__class_metadata(),
             resources: TaskResourceConstraints::UNLIMITED,
+            capabilities: Default::default(),
+            wasi: Default::default(),
         }
     }
 }
 
+impl TaskConstraints {
+    pub fn check_inputs(&self) -> Result<(), TaskError> {
+        match &self.inputs_class {
+            Some(class) => check_class(class, &self.inputs),
+            None => Ok(()),
+        }
+    }
+
+    pub fn check_outputs(&self, outputs: &ObjectData) -> Result<(), TaskError> {
+        check_class(&self.outputs, outputs)
+    }
+
+    // note: the next task is fed this task's outputs
+    pub fn check_next(&self, next: &Self) -> Result<(), TaskError> {
+        match &next.inputs_class {
+            Some(class) if class != &self.outputs => Err(TaskError::TypeMismatch {
+                expected: format!("{:?}", &class.name),
+                found: format!("{:?}", &self.outputs.name),
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
+// note: the fields are checked recursively, as the names are given by the guest
+fn check_class(expected: &ClassMetadata, object: &ObjectData) -> Result<(), TaskError> {
+    let mismatch = || TaskError::TypeMismatch {
+        expected: format!("{:?}", &expected.name),
+        found: format!("{:?}", &object.name),
+    };
+    if expected.name != object.name {
+        return Err(mismatch());
+    }
+
+    // note: a leaf cannot hold any fields
+    let children = expected.leaf.children.as_deref().unwrap_or_default();
+    match &object.value {
+        ObjectValue::Object(fields) if fields.len() == children.len() => children
+            .iter()
+            .zip(fields)
+            .try_for_each(|(child, field)| check_class(child, field)),
+        ObjectValue::Object(_) => Err(mismatch()),
+        _ if children.is_empty() => Ok(()),
+        _ => Err(mismatch()),
+    }
+}
+
 impl IsSigned for TaskConstraints {}
+
+#[cfg(test)]
+mod tests {
+    use crate::task_exception::TaskException;
+
+    use super::*;
+
+    #[test]
+    fn test_check_class() {
+        let exception = TaskException {
+            status: "Trap".to_string(),
+            message: "test".to_string(),
+        };
+        let class = TaskException::__class_metadata();
+
+        assert!(check_class(&class, &exception.__into_object_data()).is_ok());
+        assert!(check_class(&class, &().__into_object_data()).is_err());
+    }
+
+    #[test]
+    fn test_check_class_fields() {
+        let class = TaskException::__class_metadata();
+
+        // note: the object is named after the class, but has no fields
+        let object = ObjectData {
+            name: class.name.clone(),
+            ..().__into_object_data()
+        };
+        assert!(check_class(&class, &object).is_err());
+    }
+}
@@ -0,0 +1,132 @@
+use bytecheck::CheckBytes;
+use ipis::core::signed::IsSigned;
+use rkyv::{Archive, Deserialize, Serialize};
+
+use crate::task_status::TaskStatus;
+
+#[derive(Clone, Debug, PartialEq, Eq, Archive, Serialize, Deserialize)]
+#[archive(compare(PartialEq))]
+#[archive_attr(derive(CheckBytes, Debug, PartialEq))]
+pub enum TaskError {
+    // the error reported by the guest itself
+    Guest {
+        message: String,
+        stderr: String,
+    },
+    Trap {
+        code: Option<String>,
+        message: String,
+        backtrace: Vec<TaskErrorFrame>,
+        last_syscall: Option<String>,
+        stderr: String,
+    },
+    SyscallFatal,
+    // the program is rejected before instantiation
+    Rejected {
+        reason: ModuleRejection,
+    },
+    TypeMismatch {
+        expected: String,
+        found: String,
+    },
+    DeadlineExceeded,
+    OutOfFuel,
+    OutOfMemory,
+    Cancelled,
+    // note: the name is a dot-separated path of the failed child task
+    Child {
+        name: String,
+        status: TaskStatus,
+        message: String,
+    },
+    Stage {
+        index: u32,
+        status: TaskStatus,
+        message: String,
+    },
+    Kernel {
+        message: String,
+    },
+}
+
+impl TaskError {
+    pub fn kernel(error: impl ::core::fmt::Display) -> Self {
+        Self::Kernel {
+            message: error.to_string(),
+        }
+    }
+}
+
+impl ::core::fmt::Display for TaskError {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+        match self {
+            Self::Guest { message, .. } => write!(f, "{message}"),
+            Self::Trap { message, .. } => write!(f, "trap: {message}"),
+            Self::SyscallFatal => write!(f, "fatal error"),
+            Self::Rejected { reason } => write!(f, "program rejected: {reason}"),
+            Self::TypeMismatch { expected, found } => {
+                write!(f, "type mismatch: expected {expected}, found {found}")
+            }
+            Self::DeadlineExceeded => write!(f, "task deadline exceeded"),
+            Self::OutOfFuel => write!(f, "task out of fuel"),
+            Self::OutOfMemory => write!(f, "task memory limit exceeded"),
+            Self::Cancelled => write!(f, "task cancelled"),
+            Self::Child { name, message, .. } => write!(f, "child task {name} failed: {message}"),
+            Self::Stage { index, message, .. } => {
+                write!(f, "pipeline stage {index} failed: {message}")
+            }
+            Self::Kernel { message } => write!(f, "{message}"),
+        }
+    }
+}
+
+impl ::std::error::Error for TaskError {}
+
+impl IsSigned for TaskError {}
+
+#[derive(Clone, Debug, PartialEq, Eq, Archive, Serialize, Deserialize)]
+#[archive(compare(PartialEq))]
+#[archive_attr(derive(CheckBytes, Debug, PartialEq))]
+pub enum ModuleRejection {
+    MissingExport { name: String },
+    MismatchedExport { name: String },
+    UnknownImport { module: String, name: String },
+    MismatchedImport { module: String, name: String },
+}
+
+impl ::core::fmt::Display for ModuleRejection {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+        match self {
+            Self::MissingExport { name } => write!(f, "missing export: {name}"),
+            Self::MismatchedExport { name } => write!(f, "mismatched export: {name}"),
+            Self::UnknownImport { module, name } => write!(f, "unknown import: {module}::{name}"),
+            Self::MismatchedImport { module, name } => {
+                write!(f, "mismatched import: {module}::{name}")
+            }
+        }
+    }
+}
+
+impl ::std::error::Error for ModuleRejection {}
+
+#[derive(Clone, Debug, PartialEq, Eq, Archive, Serialize, Deserialize)]
+#[archive(compare(PartialEq))]
+#[archive_attr(derive(CheckBytes, Debug, PartialEq))]
+pub struct TaskErrorFrame {
+    pub module: Option<String>,
+    pub func_index: u32,
+    pub func_name: Option<String>,
+    pub module_offset: Option<u64>,
+    // note: the symbols are resolved from DWARF if present
+    pub symbols: Vec<TaskErrorSymbol>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Archive, Serialize, Deserialize)]
+#[archive(compare(PartialEq))]
+#[archive_attr(derive(CheckBytes, Debug, PartialEq))]
+pub struct TaskErrorSymbol {
+    pub name: Option<String>,
+    pub file: Option<String>,
+    pub line: Option<u32>,
+    pub column: Option<u32>,
+}
@@ -0,0 +1,25 @@
+use bytecheck::CheckBytes;
+use ipis::{class::Class, core::signed::IsSigned};
+use rkyv::{Archive, Deserialize, Serialize};
+
+use crate::{task_error::TaskError, task_status::TaskStatus};
+
+// the inputs of the exception handlers
+#[derive(Class, Clone, Debug, PartialEq, Eq, Archive, Serialize, Deserialize)]
+#[archive(compare(PartialEq))]
+#[archive_attr(derive(CheckBytes, Debug, PartialEq))]
+pub struct TaskException {
+    pub status: String,
+    pub message: String,
+}
+
+impl From<&TaskError> for TaskException {
+    fn from(error: &TaskError) -> Self {
+        Self {
+            status: format!("{:?}", TaskStatus::from(error)),
+            message: error.to_string(),
+        }
+    }
+}
+
+impl IsSigned for TaskException {}
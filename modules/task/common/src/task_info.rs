@@ -0,0 +1,38 @@
+use bytecheck::CheckBytes;
+use ipis::{
+    core::{account::AccountRef, signed::IsSigned, value::chrono::DateTime},
+    path::Path,
+};
+use ipwis_modules_core_common::resource_store::ResourceId;
+use rkyv::{Archive, Deserialize, Serialize};
+
+use crate::task_status::TaskStatus;
+
+#[derive(Clone, Debug, PartialEq, Eq, Archive, Serialize, Deserialize)]
+#[archive(compare(PartialEq))]
+#[archive_attr(derive(CheckBytes, Debug, PartialEq))]
+pub struct TaskInfo {
+    pub id: ResourceId,
+    pub guarantee: AccountRef,
+    pub program: Option<Path>,
+    pub created_date: DateTime,
+    pub started_date: Option<DateTime>,
+    pub finished_date: Option<DateTime>,
+    pub status: TaskStatus,
+    pub fuel_consumed: Option<u64>,
+    pub resources: Vec<String>,
+    // note: the per-child status of the task graphs
+    pub children: Vec<TaskChildInfo>,
+}
+
+impl IsSigned for TaskInfo {}
+
+#[derive(Clone, Debug, PartialEq, Eq, Archive, Serialize, Deserialize)]
+#[archive(compare(PartialEq))]
+#[archive_attr(derive(CheckBytes, Debug, PartialEq))]
+pub struct TaskChildInfo {
+    pub name: String,
+    pub status: TaskStatus,
+}
+
+impl IsSigned for TaskChildInfo {}
@@ -0,0 +1,30 @@
+use bytecheck::CheckBytes;
+use ipis::core::signed::IsSigned;
+use rkyv::{Archive, Deserialize, Serialize};
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Archive, Serialize, Deserialize)]
+#[archive(compare(PartialEq))]
+#[archive_attr(derive(CheckBytes, Debug, PartialEq))]
+pub struct TaskLogs {
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+    // note: the number of the oldest bytes evicted from the buffers
+    pub dropped: u64,
+    // note: the logs of the task graphs' and pipelines' members
+    pub children: Vec<TaskChildLogs>,
+}
+
+impl IsSigned for TaskLogs {}
+
+#[derive(Clone, Debug, PartialEq, Eq, Archive, Serialize, Deserialize)]
+#[archive(compare(PartialEq))]
+#[archive_attr(derive(CheckBytes, Debug, PartialEq))]
+pub struct TaskChildLogs {
+    // note: the name is a dot-separated path of the member
+    pub name: String,
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+    pub dropped: u64,
+}
+
+impl IsSigned for TaskChildLogs {}
@@ -0,0 +1,38 @@
+use bytecheck::CheckBytes;
+use ipis::{
+    core::{account::GuarantorSigned, data::Data, signed::IsSigned},
+    path::Path,
+};
+use rkyv::{Archive, Deserialize, Serialize};
+
+use crate::{task::Task, task_constraints::TaskConstraints};
+
+#[derive(Clone, Debug, PartialEq, Archive, Serialize, Deserialize)]
+#[archive_attr(derive(CheckBytes, Debug, PartialEq))]
+pub struct TaskPipeline {
+    // note: each stage's outputs are fed to the next stage's inputs
+    pub stages: Vec<TaskPipelineStage>,
+}
+
+impl IsSigned for TaskPipeline {}
+
+#[derive(Clone, Debug, PartialEq, Archive, Serialize, Deserialize)]
+#[archive_attr(derive(CheckBytes, Debug, PartialEq))]
+pub struct TaskPipelineStage {
+    pub program: Data<GuarantorSigned, Path>,
+    // note: the inputs are replaced with the previous stage's outputs,
+    //       except for the first stage
+    pub constraints: TaskConstraints,
+}
+
+impl TaskPipelineStage {
+    pub fn to_task(&self) -> Task {
+        Task {
+            constraints: self.constraints.clone(),
+            program: Some(self.program.clone()),
+            ..Task::new_sandbox()
+        }
+    }
+}
+
+impl IsSigned for TaskPipelineStage {}
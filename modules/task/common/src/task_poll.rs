@@ -1,17 +1,17 @@
 use bytecheck::CheckBytes;
-use ipis::{
-    core::{signed::IsSigned, value::text::Text},
-    object::data::ObjectData,
-};
+use ipis::{core::signed::IsSigned, object::data::ObjectData};
 use rkyv::{Archive, Deserialize, Serialize};
 
+use crate::{task_error::TaskError, task_info::TaskChildInfo};
+
 #[derive(Clone, Debug, PartialEq, Archive, Serialize, Deserialize)]
 #[archive(compare(PartialEq))]
 #[archive_attr(derive(CheckBytes, Debug, PartialEq))]
 pub enum TaskPoll {
-    Pending,
+    // note: the per-child status of the task graphs
+    Pending { children: Vec<TaskChildInfo> },
     Ready(Box<ObjectData>),
-    Trap(Text),
+    Failed(TaskError),
 }
 
 impl IsSigned for TaskPoll {}
@@ -7,12 +7,90 @@ use rkyv::{Archive, Deserialize, Serialize};
 #[archive_attr(derive(CheckBytes, Debug, PartialEq))]
 pub struct TaskResourceConstraints {
     pub due_date: DateTime,
+    pub fuel: Option<u64>,
+    pub max_memory: Option<u64>,
+    pub max_table_elements: Option<u32>,
+    pub max_instances: Option<u32>,
 }
 
 impl TaskResourceConstraints {
     pub const UNLIMITED: Self = TaskResourceConstraints {
         due_date: DateTime::MAX_DATETIME,
+        fuel: None,
+        max_memory: None,
+        max_table_elements: None,
+        max_instances: None,
     };
+
+    pub fn narrow(&self, other: &Self) -> Self {
+        Self {
+            due_date: (&self.due_date).min(&other.due_date).clone(),
+            fuel: narrow_limit(self.fuel, other.fuel),
+            max_memory: narrow_limit(self.max_memory, other.max_memory),
+            max_table_elements: narrow_limit(self.max_table_elements, other.max_table_elements),
+            max_instances: narrow_limit(self.max_instances, other.max_instances),
+        }
+    }
+}
+
+fn narrow_limit<T>(a: Option<T>, b: Option<T>) -> Option<T>
+where
+    T: Ord,
+{
+    // note: no limit means unlimited
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
 }
 
 impl IsSigned for TaskResourceConstraints {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_narrow_limit() {
+        assert_eq!(narrow_limit::<u64>(None, None), None);
+        assert_eq!(narrow_limit(Some(3u64), None), Some(3));
+        assert_eq!(narrow_limit(None, Some(3u64)), Some(3));
+        assert_eq!(narrow_limit(Some(3u64), Some(5)), Some(3));
+        assert_eq!(narrow_limit(Some(5u64), Some(3)), Some(3));
+    }
+
+    #[test]
+    fn test_narrow() {
+        let now = DateTime::now();
+        let parent = TaskResourceConstraints {
+            due_date: now.clone(),
+            fuel: Some(100),
+            max_memory: None,
+            max_table_elements: Some(10),
+            max_instances: Some(1),
+        };
+        let child = TaskResourceConstraints {
+            fuel: Some(1000),
+            max_memory: Some(4096),
+            max_table_elements: Some(5),
+            ..TaskResourceConstraints::UNLIMITED
+        };
+
+        assert_eq!(
+            parent.narrow(&child),
+            TaskResourceConstraints {
+                due_date: now,
+                fuel: Some(100),
+                max_memory: Some(4096),
+                max_table_elements: Some(5),
+                max_instances: Some(1),
+            },
+        );
+    }
+
+    #[test]
+    fn test_narrow_unlimited() {
+        let parent = TaskResourceConstraints::UNLIMITED;
+        assert_eq!(parent.narrow(&parent), parent);
+    }
+}
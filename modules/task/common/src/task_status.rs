@@ -0,0 +1,48 @@
+use bytecheck::CheckBytes;
+use ipis::core::signed::IsSigned;
+use rkyv::{Archive, Deserialize, Serialize};
+
+use crate::{task_error::TaskError, task_poll::TaskPoll};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Archive, Serialize, Deserialize)]
+#[archive(compare(PartialEq))]
+#[archive_attr(derive(CheckBytes, Debug, PartialEq))]
+pub enum TaskStatus {
+    Running,
+    Ready,
+    Trap,
+    Cancelled,
+    TimedOut,
+    OutOfFuel,
+    OutOfMemory,
+}
+
+impl From<&TaskPoll> for TaskStatus {
+    fn from(poll: &TaskPoll) -> Self {
+        match poll {
+            TaskPoll::Pending { .. } => Self::Running,
+            TaskPoll::Ready(_) => Self::Ready,
+            TaskPoll::Failed(error) => error.into(),
+        }
+    }
+}
+
+impl From<&TaskError> for TaskStatus {
+    fn from(error: &TaskError) -> Self {
+        match error {
+            TaskError::Guest { .. }
+            | TaskError::Trap { .. }
+            | TaskError::SyscallFatal
+            | TaskError::Rejected { .. }
+            | TaskError::TypeMismatch { .. }
+            | TaskError::Kernel { .. } => Self::Trap,
+            TaskError::DeadlineExceeded => Self::TimedOut,
+            TaskError::OutOfFuel => Self::OutOfFuel,
+            TaskError::OutOfMemory => Self::OutOfMemory,
+            TaskError::Cancelled => Self::Cancelled,
+            TaskError::Child { status, .. } | TaskError::Stage { status, .. } => *status,
+        }
+    }
+}
+
+impl IsSigned for TaskStatus {}
@@ -0,0 +1,12 @@
+use bytecheck::CheckBytes;
+use ipis::core::signed::IsSigned;
+use rkyv::{Archive, Deserialize, Serialize};
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Archive, Serialize, Deserialize)]
+#[archive(compare(PartialEq))]
+#[archive_attr(derive(CheckBytes, Debug, PartialEq))]
+pub struct TaskWait {
+    pub timeout_ms: Option<u64>,
+}
+
+impl IsSigned for TaskWait {}
@@ -0,0 +1,15 @@
+use bytecheck::CheckBytes;
+use ipis::core::signed::IsSigned;
+use rkyv::{Archive, Deserialize, Serialize};
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Archive, Serialize, Deserialize)]
+#[archive(compare(PartialEq))]
+#[archive_attr(derive(CheckBytes, Debug, PartialEq))]
+pub struct TaskWasi {
+    pub args: Vec<String>,
+    pub envs: Vec<(String, String)>,
+    // note: each directory is mapped to an empty host directory, which is removed after the task
+    pub scratch_dirs: Vec<String>,
+}
+
+impl IsSigned for TaskWasi {}
@@ -6,3 +6,4 @@ pub mod interrupt_id;
 #[cfg(target_os = "wasi")]
 pub mod interrupt_id_wasi;
 pub mod program;
+pub mod program_artifact;
@@ -0,0 +1,13 @@
+use bytecheck::CheckBytes;
+use ipis::{core::signed::IsSigned, path::Path};
+use rkyv::{Archive, Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Eq, Archive, Serialize, Deserialize)]
+#[archive(compare(PartialEq))]
+#[archive_attr(derive(CheckBytes, Debug, PartialEq))]
+pub struct ProgramArtifact {
+    pub source: Path,
+    pub bytes: Vec<u8>,
+}
+
+impl IsSigned for ProgramArtifact {}